@@ -3,6 +3,7 @@
 //! Minecraft-generated files.
 
 extern crate criterion;
+extern crate flate2;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
@@ -14,7 +15,9 @@ use std::io::{self, Read, Seek, SeekFrom};
 
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 
-use nbt::de::from_gzip_reader;
+use flate2::read::GzDecoder;
+
+use nbt::de::{from_gzip_reader, from_reader, read_f64_list};
 use nbt::ser::to_writer;
 
 mod data {
@@ -61,11 +64,34 @@ where
     group.finish();
 }
 
+fn bench_read_list(filename: &str, path: &str, c: &mut Criterion) {
+    let mut file = File::open(filename).unwrap();
+    let mut contents = Vec::new();
+    GzDecoder::new(&mut file)
+        .read_to_end(&mut contents)
+        .unwrap();
+
+    let mut group = c.benchmark_group(filename);
+    group.throughput(Throughput::Bytes(contents.len() as u64));
+    group.bench_function("Read List As Struct", |b| {
+        b.iter(|| {
+            let _: data::PlayerData = from_reader(&contents[..]).unwrap();
+        })
+    });
+    group.bench_function("Read List Directly", |b| {
+        b.iter(|| {
+            read_f64_list(&contents[..], path).unwrap();
+        })
+    });
+    group.finish();
+}
+
 fn bench(c: &mut Criterion) {
     bench_serialize::<data::Big1>("tests/big1.nbt", c);
     bench_serialize::<data::PlayerData>("tests/simple_player.dat", c);
     bench_serialize::<data::PlayerData>("tests/complex_player.dat", c);
     bench_serialize::<data::Level>("tests/level.dat", c);
+    bench_read_list("tests/complex_player.dat", "Pos", c);
 }
 
 criterion_group!(benches, bench);
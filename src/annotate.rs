@@ -0,0 +1,127 @@
+//! Human-readable dumps of raw NBT data, for debugging malformed files.
+
+use std::fmt::Write;
+use std::io;
+
+use byteorder::ReadBytesExt;
+
+use error::{Error, Result};
+use options::DEFAULT_MAX_DEPTH;
+use raw::{self, Endianness};
+use value::tag_name_for_id;
+
+/// Walks the raw bytes of an (uncompressed) NBT document and produces a
+/// listing of each tag, name, and value along with the offset at which it
+/// was found, e.g.
+///
+/// ```text
+/// offset 0: 0x0a TAG_Compound
+/// offset 1: name "hello world"
+/// offset 14:   0x08 TAG_String
+/// ```
+///
+/// Parsing stops at the first error, which is included in the listing along
+/// with the offset at which it occurred, so that everything leading up to a
+/// corrupt region of the file can still be inspected.
+pub fn annotate(data: &[u8]) -> Result<String> {
+    let mut src = io::Cursor::new(data);
+    let mut out = String::new();
+    if let Err(e) = annotate_entry(&mut src, &mut out, 0) {
+        line(&mut out, src.position(), 0, &format!("error: {}", e));
+    }
+    Ok(out)
+}
+
+/// Annotates a single named entry, returning `false` if it was a `TAG_End`.
+fn annotate_entry(src: &mut io::Cursor<&[u8]>, out: &mut String, depth: usize) -> Result<bool> {
+    let offset = src.position();
+    let tag = src.read_u8()?;
+    let desc = format!("0x{:02x} {}", tag, tag_name(tag)?);
+    line(out, offset, depth, &desc);
+    if tag == 0x00 {
+        return Ok(false);
+    }
+    let offset = src.position();
+    let name = raw::read_bare_string(src, Endianness::BigEndian)?;
+    line(out, offset, depth, &format!("name {:?}", name));
+    annotate_payload(src, out, tag, depth)?;
+    Ok(true)
+}
+
+fn annotate_payload(
+    src: &mut io::Cursor<&[u8]>,
+    out: &mut String,
+    tag: u8,
+    depth: usize,
+) -> Result<()> {
+    let offset = src.position();
+    // `depth` also counts the compounds and lists this one is nested inside.
+    if (tag == 0x09 || tag == 0x0a) && depth >= DEFAULT_MAX_DEPTH {
+        return Err(Error::DepthLimitExceeded);
+    }
+    let desc = match tag {
+        0x01 => format!("value {}", raw::read_bare_byte(src)?),
+        0x02 => format!(
+            "value {}",
+            raw::read_bare_short(src, Endianness::BigEndian)?
+        ),
+        0x03 => format!("value {}", raw::read_bare_int(src, Endianness::BigEndian)?),
+        0x04 => format!("value {}", raw::read_bare_long(src, Endianness::BigEndian)?),
+        0x05 => format!(
+            "value {}",
+            raw::read_bare_float(src, Endianness::BigEndian)?
+        ),
+        0x06 => format!(
+            "value {}",
+            raw::read_bare_double(src, Endianness::BigEndian)?
+        ),
+        0x07 => {
+            let vals = raw::read_bare_byte_array(src, Endianness::BigEndian)?;
+            format!("{} bytes {:?}", vals.len(), vals)
+        }
+        0x08 => format!(
+            "value {:?}",
+            raw::read_bare_string(src, Endianness::BigEndian)?
+        ),
+        0x09 => {
+            let (id, len) = raw::read_list_header(src, Endianness::BigEndian)?;
+            let desc = format!("{} entries of type 0x{:02x} {}", len, id, tag_name(id)?);
+            line(out, offset, depth, &desc);
+            for _ in 0..len {
+                annotate_payload(src, out, id, depth + 1)?;
+            }
+            return Ok(());
+        }
+        0x0a => {
+            while annotate_entry(src, out, depth + 1)? {}
+            return Ok(());
+        }
+        0x0b => {
+            let vals = raw::read_bare_int_array(src, Endianness::BigEndian)?;
+            format!("{} ints {:?}", vals.len(), vals)
+        }
+        0x0c => {
+            let vals = raw::read_bare_long_array(src, Endianness::BigEndian)?;
+            format!("{} longs {:?}", vals.len(), vals)
+        }
+        e => return Err(Error::InvalidTypeId(e)),
+    };
+    line(out, offset, depth, &desc);
+    Ok(())
+}
+
+fn line(out: &mut String, offset: u64, depth: usize, desc: &str) {
+    // Writing to a `String` cannot fail.
+    let _ = writeln!(
+        out,
+        "offset {}: {:>width$}{}",
+        offset,
+        "",
+        desc,
+        width = depth * 2
+    );
+}
+
+fn tag_name(id: u8) -> Result<&'static str> {
+    tag_name_for_id(id).ok_or(Error::InvalidTypeId(id))
+}
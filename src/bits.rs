@@ -0,0 +1,83 @@
+//! Packing of small integers into the `TAG_Long_Array`s used for chunk data.
+
+/// A view of a `Value::LongArray` as a sequence of packed, fixed-width
+/// entries, as used by Minecraft for block states and heightmaps.
+///
+/// This uses the packing introduced in 1.16, where each long holds
+/// `64 / bits_per_entry` entries starting from its least significant bits,
+/// and entries never straddle two longs. Any remaining high bits of each long
+/// are left as padding.
+///
+/// ```rust
+/// use nbt::LongArrayBits;
+///
+/// let longs = LongArrayBits::pack(&[1, 2, 3], 4);
+/// assert_eq!(longs, vec![0x321]);
+/// assert_eq!(&LongArrayBits::new(&longs).unpack(4)[..3], &[1, 2, 3]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongArrayBits<'a> {
+    longs: &'a [i64],
+}
+
+impl<'a> LongArrayBits<'a> {
+    /// Creates a view of the given longs, e.g. the contents of a
+    /// `Value::LongArray`.
+    pub fn new(longs: &'a [i64]) -> LongArrayBits<'a> {
+        LongArrayBits { longs }
+    }
+
+    /// Unpacks every entry of `bits_per_entry` bits held in the longs.
+    ///
+    /// Since the number of entries is not stored in the array, this includes
+    /// any unused entries at the end of the last long, which will be zero if
+    /// the array was written by `pack`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits_per_entry` is zero or greater than 32.
+    pub fn unpack(&self, bits_per_entry: usize) -> Vec<u32> {
+        let per_long = entries_per_long(bits_per_entry);
+        let mask = (1u64 << bits_per_entry) - 1;
+        let mut values = Vec::with_capacity(self.longs.len() * per_long);
+        for &long in self.longs {
+            let long = long as u64;
+            for i in 0..per_long {
+                values.push(((long >> (i * bits_per_entry)) & mask) as u32);
+            }
+        }
+        values
+    }
+
+    /// Packs `values` into longs with `bits_per_entry` bits per entry. The
+    /// unused entries of the last long are set to zero.
+    ///
+    /// Only the lowest `bits_per_entry` bits of each value are kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits_per_entry` is zero or greater than 32.
+    pub fn pack(values: &[u32], bits_per_entry: usize) -> Vec<i64> {
+        let per_long = entries_per_long(bits_per_entry);
+        let mask = (1u64 << bits_per_entry) - 1;
+        values
+            .chunks(per_long)
+            .map(|chunk| {
+                let mut long = 0u64;
+                for (i, &value) in chunk.iter().enumerate() {
+                    long |= (u64::from(value) & mask) << (i * bits_per_entry);
+                }
+                long as i64
+            })
+            .collect()
+    }
+}
+
+fn entries_per_long(bits_per_entry: usize) -> usize {
+    assert!(
+        bits_per_entry > 0 && bits_per_entry <= 32,
+        "bits per entry must be between 1 and 32, not {}",
+        bits_per_entry
+    );
+    64 / bits_per_entry
+}
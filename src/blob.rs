@@ -1,16 +1,21 @@
 use crate::Map;
 use std::fmt;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::mem;
 use std::ops::Index;
+use std::path::{Path, PathBuf};
 
-use byteorder::WriteBytesExt;
-use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::read::{GzDecoder, MultiGzDecoder, ZlibDecoder};
 use flate2::write::{GzEncoder, ZlibEncoder};
-use flate2::Compression;
 
-use error::{Error, Result};
-use raw;
-use value::Value;
+use compression::{self, Compression};
+
+use error::{DecompressReader, Error, Result, RootNames};
+use options::{self, ReadOptions};
+use raw::{self, Endianness, Flavor, Format};
+use snbt;
+use value::{self, Value};
 
 /// A generic, complete object in Named Binary Tag format.
 ///
@@ -61,80 +66,386 @@ impl Blob {
         }
     }
 
+    /// Create a new NBT file format representation with an empty name and a
+    /// single entry.
+    ///
+    /// ```
+    /// # fn main() -> nbt::Result<()> {
+    /// use nbt::{Blob, Value};
+    ///
+    /// let blob = Blob::with("Health", 20i16)?;
+    /// assert_eq!(blob["Health"], Value::Short(20));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Like `insert`, this returns an error if the value is a heterogeneous
+    /// `Value::List`.
+    pub fn with<S, V>(name: S, value: V) -> Result<Blob>
+    where
+        S: Into<String>,
+        V: Into<Value>,
+    {
+        let mut blob = Blob::new();
+        blob.insert(name, value)?;
+        Ok(blob)
+    }
+
+    /// Create a new NBT file format representation with an empty name, with
+    /// space for at least `capacity` entries before reallocating.
+    pub fn with_capacity(capacity: usize) -> Blob {
+        Blob {
+            title: "".to_string(),
+            content: Map::with_capacity(capacity),
+        }
+    }
+
+    /// The name of the root compound of this `Blob`.
+    ///
+    /// This is written by `Blob::to_writer`, but not when the `Blob` is
+    /// serialized through serde, which takes the name from the header passed
+    /// to e.g. `nbt::to_writer` instead.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
     /// Extracts an `Blob` object from an `io::Read` source.
     pub fn from_reader<R>(src: &mut R) -> Result<Blob>
     where
-        R: io::Read,
+        R: ?Sized + io::Read,
     {
-        let (tag, title) = raw::emit_next_header(src)?;
+        Blob::from_reader_with(src, &ReadOptions::default())
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source, parsing it
+    /// according to the given options.
+    pub fn from_reader_with<R>(src: &mut R, options: &ReadOptions) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let mut blob = Blob::new();
+        blob.read_into_with(src, options)?;
+        Ok(blob)
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source of little-endian
+    /// data, as used by Bedrock Edition.
+    pub fn from_le_reader<R>(src: &mut R) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let options = ReadOptions {
+            endian: Endianness::LittleEndian,
+            ..Default::default()
+        };
+        Blob::from_reader_with(src, &options)
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source in the network
+    /// format of Bedrock Edition, with little-endian values and VarInt lengths
+    /// and integers.
+    pub fn from_varint_reader<R>(src: &mut R) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let options = ReadOptions {
+            endian: Endianness::LittleEndian,
+            flavor: Flavor::VarInt,
+            ..Default::default()
+        };
+        Blob::from_reader_with(src, &options)
+    }
+
+    /// Replaces the contents of this `Blob` with those read from an
+    /// `io::Read` source, reusing its allocated capacity. This avoids
+    /// reallocating when many files are read one after another.
+    ///
+    /// If an error is returned, the contents of this `Blob` are unspecified.
+    pub fn read_into<R>(&mut self, src: &mut R) -> Result<()>
+    where
+        R: ?Sized + io::Read,
+    {
+        self.read_into_with(src, &ReadOptions::default())
+    }
+
+    fn read_into_with<R>(&mut self, src: &mut R, options: &ReadOptions) -> Result<()>
+    where
+        R: ?Sized + io::Read,
+    {
+        let tag = raw::read_bare_byte(src)?;
         // Although it would be possible to read NBT format files composed of
         // arbitrary objects using the current API, by convention all files
         // have a top-level Compound.
         if tag != 0x0a {
             return Err(Error::NoRootCompound);
         }
-        let content = Value::from_reader(tag, src)?;
-        match content {
-            Value::Compound(map) => Ok(Blob {
-                title,
-                content: map,
-            }),
-            _ => Err(Error::NoRootCompound),
+        // A name longer than the data that follows is a common symptom of
+        // reading something other than uncompressed NBT, so say so.
+        let title = raw::read_bare_string(src, options.format()).map_err(|e| match e {
+            Error::IncompleteNbtValue => Error::IncompleteRootName,
+            e => e,
+        })?;
+        if options.max_depth == 0 {
+            return Err(Error::DepthLimitExceeded);
+        }
+        let mut nodes = options.max_nodes;
+        options::count_nodes(&mut nodes, 1)?;
+        self.title = title;
+        self.content.clear();
+        value::read_compound_into(
+            &mut self.content,
+            src,
+            options.format(),
+            options.max_depth - 1,
+            &mut nodes,
+            options.tolerate_missing_end,
+        )
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source, and checks that
+    /// its root compound has the `expected` name.
+    ///
+    /// Returns `Error::UnexpectedRootName` if the name is different, which can
+    /// help to catch the wrong kind of file being read.
+    pub fn from_reader_expect_name<R>(src: &mut R, expected: &str) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let blob = Blob::from_reader(src)?;
+        if blob.title != expected {
+            return Err(Error::UnexpectedRootName(Box::new(RootNames {
+                found: blob.title,
+                expected: expected.to_string(),
+            })));
         }
+        Ok(blob)
     }
 
     /// Extracts an `Blob` object from an `io::Read` source that is
     /// compressed using the Gzip format.
     pub fn from_gzip_reader<R>(src: &mut R) -> Result<Blob>
     where
-        R: io::Read,
+        R: ?Sized + io::Read,
     {
-        // Reads the gzip header, and fails if it is incorrect.
-        let mut data = GzDecoder::new(src);
-        Blob::from_reader(&mut data)
+        Blob::from_gzip_reader_endian(src, Endianness::BigEndian)
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source that is
+    /// compressed using the Gzip format, with multi-byte values in the given
+    /// byte order.
+    pub fn from_gzip_reader_endian<R>(src: &mut R, endian: Endianness) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let options = ReadOptions {
+            endian,
+            ..Default::default()
+        };
+        Blob::from_reader_with(&mut DecompressReader(GzDecoder::new(src)), &options)
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source that is
+    /// compressed using the Gzip format, and which may consist of several
+    /// concatenated Gzip members.
+    ///
+    /// All of the members are decompressed in order and read as a single NBT
+    /// document.
+    pub fn from_multigzip_reader<R>(src: &mut R) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        Blob::from_reader(&mut DecompressReader(MultiGzDecoder::new(src)))
     }
 
     /// Extracts an `Blob` object from an `io::Read` source that is
     /// compressed using the zlib format.
     pub fn from_zlib_reader<R>(src: &mut R) -> Result<Blob>
     where
-        R: io::Read,
+        R: ?Sized + io::Read,
+    {
+        Blob::from_zlib_reader_endian(src, Endianness::BigEndian)
+    }
+
+    /// Extracts an `Blob` object from an `io::Read` source that is
+    /// compressed using the zlib format, with multi-byte values in the given
+    /// byte order.
+    pub fn from_zlib_reader_endian<R>(src: &mut R, endian: Endianness) -> Result<Blob>
+    where
+        R: ?Sized + io::Read,
+    {
+        let options = ReadOptions {
+            endian,
+            ..Default::default()
+        };
+        Blob::from_reader_with(&mut DecompressReader(ZlibDecoder::new(src)), &options)
+    }
+
+    /// Reads every `.dat` and `.nbt` file in a directory, such as the
+    /// `playerdata` folder of a save, detecting whether each is compressed
+    /// with Gzip, zlib, or not at all. Subdirectories are not searched.
+    ///
+    /// Files are read one at a time as the iterator advances, in order of
+    /// their paths, and each is yielded with its own result. An error reading
+    /// the directory itself is yielded along with the directory's path.
+    pub fn from_dir<P>(dir: P) -> impl Iterator<Item = (PathBuf, Result<Blob>)>
+    where
+        P: AsRef<Path>,
     {
-        Blob::from_reader(&mut ZlibDecoder::new(src))
+        let dir = dir.as_ref();
+        let mut paths = Vec::new();
+        let mut errors = Vec::new();
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => paths.push(entry.path()),
+                        Err(e) => errors.push((dir.to_path_buf(), Err(Error::from(e)))),
+                    }
+                }
+            }
+            Err(e) => errors.push((dir.to_path_buf(), Err(Error::from(e)))),
+        }
+        paths.retain(|path| {
+            let nbt_ext = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => ext == "dat" || ext == "nbt",
+                None => false,
+            };
+            nbt_ext && path.is_file()
+        });
+        paths.sort();
+
+        errors.into_iter().chain(paths.into_iter().map(|path| {
+            let blob = Blob::from_detected_file(&path);
+            (path, blob)
+        }))
+    }
+
+    /// Reads a file which may or may not be compressed.
+    fn from_detected_file(path: &Path) -> Result<Blob> {
+        let mut src = io::BufReader::new(File::open(path)?);
+        match compression::detect_compression(src.fill_buf()?) {
+            Some(Compression::Gzip) => Blob::from_gzip_reader(&mut src),
+            Some(Compression::Zlib) => Blob::from_zlib_reader(&mut src),
+            None => Blob::from_reader(&mut src),
+        }
     }
 
     /// Writes the binary representation of this `Blob` to an `io::Write`
     /// destination.
-    pub fn to_writer<W>(&self, mut dst: &mut W) -> Result<()>
+    pub fn to_writer<W>(&self, dst: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.to_writer_endian(dst, Endianness::BigEndian)
+    }
+
+    /// Writes the binary representation of this `Blob` to an `io::Write`
+    /// destination, with multi-byte values in the given byte order, e.g.
+    /// `Endianness::LittleEndian` for Bedrock Edition data.
+    pub fn to_writer_endian<W>(&self, dst: &mut W, endian: Endianness) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.to_writer_format(dst, endian.into())
+    }
+
+    fn to_writer_format<W>(&self, mut dst: &mut W, format: Format) -> Result<()>
     where
-        W: io::Write,
+        W: ?Sized + io::Write,
     {
-        dst.write_u8(0x0a)?;
-        raw::write_bare_string(&mut dst, &self.title)?;
+        raw::write_header(&mut dst, 0x0a, &self.title, format)?;
         for (name, ref nbt) in self.content.iter() {
-            dst.write_u8(nbt.id())?;
-            raw::write_bare_string(&mut dst, name)?;
-            nbt.to_writer(&mut dst)?;
+            raw::write_header(&mut dst, nbt.id(), name, format)?;
+            nbt.write_payload(&mut dst, format)?;
         }
         raw::close_nbt(&mut dst)
     }
 
+    /// Writes the binary representation of this `Blob` in the network format
+    /// of Bedrock Edition, with little-endian values and VarInt lengths and
+    /// integers, to an `io::Write` destination.
+    pub fn to_varint_writer<W>(&self, dst: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.to_writer_format(dst, Format::NETWORK)
+    }
+
+    /// Writes the contents of this `Blob` as an SNBT compound, as
+    /// `Value::to_snbt` does. The name of the root compound is not included.
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        snbt::write_compound(&mut out, &self.content);
+        out
+    }
+
+    /// Writes the binary representation of this `Blob` to an `io::Write`
+    /// destination as `to_writer` does, returning the number of bytes
+    /// written. Unlike `len_bytes`, this counts the bytes actually written,
+    /// e.g. for accounting for the sectors of a region file.
+    pub fn write_counted<W>(&self, dst: &mut W) -> Result<usize>
+    where
+        W: ?Sized + io::Write,
+    {
+        let mut dst = Counter {
+            inner: dst,
+            count: 0,
+        };
+        self.to_writer(&mut dst)?;
+        Ok(dst.count as usize)
+    }
+
+    /// Writes the little-endian binary representation of this `Blob`, as used
+    /// by Bedrock Edition, to an `io::Write` destination.
+    pub fn to_le_writer<W>(&self, dst: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.to_writer_endian(dst, Endianness::LittleEndian)
+    }
+
     /// Writes the binary representation of this `Blob`, compressed using
     /// the Gzip format, to an `io::Write` destination.
     pub fn to_gzip_writer<W>(&self, dst: &mut W) -> Result<()>
     where
-        W: io::Write,
+        W: ?Sized + io::Write,
     {
-        self.to_writer(&mut GzEncoder::new(dst, Compression::default()))
+        self.to_gzip_writer_endian(dst, Endianness::BigEndian)
+    }
+
+    /// Writes the binary representation of this `Blob`, compressed using
+    /// the Gzip format, to an `io::Write` destination, with multi-byte values in
+    /// the given byte order.
+    pub fn to_gzip_writer_endian<W>(&self, dst: &mut W, endian: Endianness) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let mut encoder = GzEncoder::new(dst, flate2::Compression::default());
+        self.to_writer_endian(&mut encoder, endian)?;
+        encoder.finish()?;
+        Ok(())
     }
 
     /// Writes the binary representation of this `Blob`, compressed using
     /// the Zlib format, to an `io::Write` dst.
     pub fn to_zlib_writer<W>(&self, dst: &mut W) -> Result<()>
     where
-        W: io::Write,
+        W: ?Sized + io::Write,
     {
-        self.to_writer(&mut ZlibEncoder::new(dst, Compression::default()))
+        self.to_zlib_writer_endian(dst, Endianness::BigEndian)
+    }
+
+    /// Writes the binary representation of this `Blob`, compressed using
+    /// the Zlib format, to an `io::Write` destination, with multi-byte values in
+    /// the given byte order.
+    pub fn to_zlib_writer_endian<W>(&self, dst: &mut W, endian: Endianness) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let mut encoder = ZlibEncoder::new(dst, flate2::Compression::default());
+        self.to_writer_endian(&mut encoder, endian)?;
+        encoder.finish()?;
+        Ok(())
     }
 
     /// Insert an `Value` with a given name into this `Blob` object. This
@@ -166,6 +477,55 @@ impl Blob {
         Ok(())
     }
 
+    /// Merges the entries of `other` into this `Blob`, recursing into
+    /// compounds present in both, so that `other` only needs to hold the
+    /// values to be added or changed.
+    ///
+    /// Returns `Error::TagMismatch` if a value would be replaced by one with a
+    /// different tag type, e.g. a `Value::Int` by a `Value::Long`, rather than
+    /// silently changing its type. This `Blob` is left unchanged in that case.
+    pub fn merge_strict(&mut self, other: &Blob) -> Result<()> {
+        let mut content = self.content.clone();
+        value::merge_compound_strict(&mut content, &other.content)?;
+        self.content = content;
+        Ok(())
+    }
+
+    /// Reserves capacity for at least `additional` more entries, to avoid
+    /// repeated reallocation when inserting many values. This is a thin
+    /// wrapper around the underlying map method of the same name.
+    pub fn reserve(&mut self, additional: usize) {
+        self.content.reserve(additional)
+    }
+
+    /// Checks that this `Blob` can be written in the NBT format, by validating
+    /// its name and every entry with `Value::validate`. This catches e.g.
+    /// heterogeneous lists nested inside compounds, which `insert` does not
+    /// check, before attempting to write anything.
+    pub fn validate(&self) -> Result<()> {
+        value::validate_string(&self.title)?;
+        for (name, nbt) in &self.content {
+            value::validate_string(name)?;
+            nbt.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Computes a hash of the contents of this `Blob`, which is stable across
+    /// runs, platforms, and map orderings (including with the
+    /// `preserve_order` feature), so that it can be used to find duplicate
+    /// chunks or entities.
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the blob's serialized form, with
+    /// the entries of every compound sorted by name.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+        // Neither writing to the hasher nor canonical serialization can fail.
+        raw::write_header(&mut hasher, 0x0a, &self.title, Endianness::BigEndian).unwrap();
+        value::write_canonical_compound(&mut hasher, &self.content).unwrap();
+        hasher.0
+    }
+
     /// Tries to get a named `Value` in the blob.
     pub fn get<S>(&self, name: S) -> Option<&Value>
     where
@@ -174,6 +534,148 @@ impl Blob {
         self.content.get(name.into())
     }
 
+    /// Returns the value named `key` if it is the only entry in this `Blob`,
+    /// e.g. the `Data` compound which wraps everything in `level.dat`.
+    pub fn unwrap_root(&self, key: &str) -> Option<&Value> {
+        if self.content.len() == 1 {
+            self.content.get(key)
+        } else {
+            None
+        }
+    }
+
+    /// Tries to get the `Value` at a dotted path through nested compounds and
+    /// lists, e.g. `"Data.Player"` or `"Data.Player.Pos.1"`, where numeric
+    /// segments index into lists. This is the form of path used by `walk`.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        self.path_with(path, '.')
+    }
+
+    /// Tries to get the `Value` at a dotted path mutably, as `get_path` does.
+    fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut segments = path.split('.');
+        let value = self.content.get_mut(segments.next()?)?;
+        segments.try_fold(value, Value::child_mut)
+    }
+
+    /// Walks a `/`-separated path through nested compounds and lists, e.g.
+    /// `"Data/Player/Pos/1"`, as `Value::path` does.
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        self.path_with(path, '/')
+    }
+
+    /// Walks a path through nested compounds and lists as `path` does, but
+    /// with segments separated by `sep` instead of `/`.
+    pub fn path_with(&self, path: &str, sep: char) -> Option<&Value> {
+        let mut segments = path.splitn(2, sep);
+        let value = self.content.get(segments.next()?)?;
+        match segments.next() {
+            Some(rest) => value.path_with(rest, sep),
+            None => Some(value),
+        }
+    }
+
+    /// Renames the value at a dotted path (see `get_path`) by replacing the
+    /// last segment of the path with `new_name`, e.g. renaming
+    /// `"Data.Player.foodLevel"` to `"Data.Player.FoodLevel"`. With the
+    /// `preserve_order` feature, the value keeps its position in its compound.
+    ///
+    /// Returns `Ok(false)` if there is no value at the path or it is an
+    /// element of a list, and `Error::UnexpectedField` if its compound already
+    /// has an entry named `new_name`.
+    pub fn rename_path(&mut self, path: &str, new_name: &str) -> Result<bool> {
+        value::validate_string(new_name)?;
+        let mut segments = path.rsplitn(2, '.');
+        let old_name = segments.next().unwrap_or(path);
+        let map = match segments.next() {
+            None => &mut self.content,
+            Some(parent) => match self.get_path_mut(parent) {
+                Some(&mut Value::Compound(ref mut map)) => map,
+                _ => return Ok(false),
+            },
+        };
+        if !map.contains_key(old_name) {
+            return Ok(false);
+        }
+        if old_name != new_name {
+            if map.contains_key(new_name) {
+                return Err(Error::UnexpectedField(new_name.to_string()));
+            }
+            *map = mem::take(map)
+                .into_iter()
+                .map(|(k, v)| {
+                    if k == old_name {
+                        (new_name.to_string(), v)
+                    } else {
+                        (k, v)
+                    }
+                })
+                .collect();
+        }
+        Ok(true)
+    }
+
+    /// Calls `f(path, value)` for every value in this `Blob`, including
+    /// nested ones, as `Value::walk` does. Paths begin with the name of an
+    /// entry of the blob, e.g. `"Data.Player"`.
+    pub fn walk<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&str, &'a Value),
+    {
+        let mut path = String::new();
+        for (name, nbt) in &self.content {
+            nbt.walk_child(&mut path, name, &mut f);
+        }
+    }
+
+    /// Returns the path of every value in this `Blob`, including nested ones,
+    /// in the form used by `walk`: compound entries are named by their keys
+    /// and list elements by their indices, e.g. `"Data.Player.Inventory.0"`.
+    pub fn all_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        self.walk(|path, _| paths.push(path.to_string()));
+        paths
+    }
+
+    /// Finds every string in this `Blob` for which `pred` returns `true`,
+    /// along with its path (see `walk`).
+    pub fn find_strings<F>(&self, pred: F) -> Vec<(String, &str)>
+    where
+        F: Fn(&str) -> bool,
+    {
+        value::find_strings_with(|f| self.walk(f), pred)
+    }
+
+    /// Writes the value at a dotted path (see `get_path`) to an `io::Write`
+    /// destination as a standalone NBT document, e.g. to extract
+    /// `"Data.Player"` from `level.dat` into its own file.
+    ///
+    /// A compound is written as the root, named after the last segment of the
+    /// path. Any other value is wrapped in an unnamed root compound, as its
+    /// only entry. Returns `Error::PathNotFound` if there is no such value.
+    pub fn write_subtree<W>(&self, dst: &mut W, path: &str) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let value = match self.get_path(path) {
+            Some(value) => value,
+            None => return Err(Error::PathNotFound(path.to_string())),
+        };
+        let name = path.rsplit('.').next().unwrap_or(path);
+        let blob = match *value {
+            Value::Compound(ref map) => Blob {
+                title: name.to_string(),
+                content: map.clone(),
+            },
+            ref other => {
+                let mut blob = Blob::new();
+                blob.content.insert(name.to_string(), other.clone());
+                blob
+            }
+        };
+        blob.to_writer(dst)
+    }
+
     /// The number of bytes this blob will serialize to, before compression
     pub fn len_bytes(&self) -> usize {
         /* compound tag + name length + TAG_End = 4 */
@@ -184,6 +686,107 @@ impl Blob {
                 .map(Value::size_of_compound_entry)
                 .sum::<usize>()
     }
+
+    /// The number of bytes by which this blob's serialized form is larger
+    /// than `other`'s, or negative if it is smaller. Like `len_bytes`, this is
+    /// the size before compression, so it only bounds the change in the size
+    /// of e.g. a compressed chunk.
+    pub fn size_delta(&self, other: &Blob) -> isize {
+        self.len_bytes() as isize - other.len_bytes() as isize
+    }
+
+    /// Gathers statistics about the uncompressed NBT document read from an
+    /// `io::Read` source, without building a `Blob` from it.
+    ///
+    /// This reads the whole document in a single pass, so it is a cheap way
+    /// to find out how large and deeply nested a file is before deciding
+    /// on the `ReadOptions` limits with which to read it. The document is
+    /// read with the byte order, flavor and `max_depth` of `options`.
+    pub fn stat<R>(src: R, options: &ReadOptions) -> Result<BlobStats>
+    where
+        R: io::Read,
+    {
+        let mut src = Counter {
+            inner: src,
+            count: 0,
+        };
+        let mut stats = BlobStats::default();
+        if raw::read_bare_byte(&mut src)? != 0x0a {
+            return Err(Error::NoRootCompound);
+        }
+        let format = options.format();
+        raw::skip_payload(&mut src, 0x08, format, 0, &mut |_, _| ())?;
+        let max_depth = options.max_depth;
+        raw::skip_payload(&mut src, 0x0a, format, max_depth, &mut |id, depth| {
+            stats.tags += 1;
+            stats.tag_counts[id as usize] += 1;
+            if id == 0x09 || id == 0x0a {
+                // The root compound is at level 1.
+                stats.max_depth = stats.max_depth.max(max_depth - depth + 1);
+            }
+        })?;
+        stats.bytes = src.count;
+        Ok(stats)
+    }
+}
+
+/// Statistics about an NBT document, as gathered by `Blob::stat`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlobStats {
+    /// The total number of tags, counting the root compound, each entry of a
+    /// compound and each element of a list, as `ReadOptions::max_nodes` does.
+    pub tags: usize,
+    /// The deepest nesting of compounds and lists, where the root compound
+    /// alone has a depth of one, as `ReadOptions::max_depth` counts it.
+    pub max_depth: usize,
+    /// The number of tags of each type, indexed by tag ID, so that e.g.
+    /// `tag_counts[0x0a]` is the number of compounds.
+    pub tag_counts: [usize; 13],
+    /// The total size of the document in bytes, before any compression.
+    pub bytes: u64,
+}
+
+/// An `io::Read` or `io::Write` adaptor which counts the bytes passed
+/// through it.
+struct Counter<T> {
+    inner: T,
+    count: u64,
+}
+
+impl<R: io::Read> io::Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<W: io::Write> io::Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A 64-bit FNV-1a hasher, which accepts its input as an `io::Write`.
+struct Fnv1a(u64);
+
+impl io::Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 impl<'a> Index<&'a str> for Blob {
@@ -214,13 +817,15 @@ impl fmt::Display for Blob {
 #[cfg(feature = "serde")]
 use serde::{self, ser::SerializeMap};
 
+/// Serializes the contents of the root compound only. The title is not
+/// serialized: the root name comes from the encoder's header, so to keep it
+/// use e.g. `nbt::to_writer(dst, &blob, Some(blob.title()))`.
 #[cfg(feature = "serde")]
 impl serde::Serialize for Blob {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        // No support for named Blobs.
         let mut state = serializer.serialize_map(Some(self.content.len()))?;
         for (k, v) in &self.content {
             state.serialize_entry(&k, &v)?;
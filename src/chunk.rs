@@ -0,0 +1,41 @@
+//! Helpers for the data stored in Minecraft chunks.
+
+use bits::LongArrayBits;
+use error::{Error, Result};
+use value::Value;
+
+/// The number of columns in a chunk, and so of entries in a heightmap.
+const HEIGHTMAP_LEN: usize = 16 * 16;
+
+/// The number of bits used for each entry of a heightmap in a world of the
+/// given height, which must be able to store every height from 0 to
+/// `world_height` inclusive.
+pub fn heightmap_bits(world_height: u32) -> usize {
+    (32 - world_height.leading_zeros()).max(1) as usize
+}
+
+/// Decodes one of the long arrays stored in a chunk's `Heightmaps` compound
+/// into the 256 heights of its columns, in `x + z * 16` order.
+///
+/// Returns `Error::TagMismatch` if the value is not a `Value::LongArray`, and
+/// `Error::IncompleteNbtValue` if it is too short to hold every column.
+pub fn decode_heightmap(value: &Value, world_height: u32) -> Result<Vec<u16>> {
+    let longs = match value.as_long_array() {
+        Some(longs) => longs,
+        None => return Err(Error::TagMismatch(value.id(), 0x0c)),
+    };
+    let mut heights = LongArrayBits::new(longs).unpack(heightmap_bits(world_height));
+    if heights.len() < HEIGHTMAP_LEN {
+        return Err(Error::IncompleteNbtValue);
+    }
+    heights.truncate(HEIGHTMAP_LEN);
+    Ok(heights.into_iter().map(|h| h as u16).collect())
+}
+
+/// Encodes the heights of a chunk's columns, in `x + z * 16` order, as a
+/// long array for its `Heightmaps` compound. This is the inverse of
+/// `decode_heightmap`.
+pub fn encode_heightmap(heights: &[u16], world_height: u32) -> Value {
+    let values: Vec<u32> = heights.iter().map(|&h| u32::from(h)).collect();
+    Value::LongArray(LongArrayBits::pack(&values, heightmap_bits(world_height)))
+}
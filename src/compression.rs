@@ -0,0 +1,73 @@
+//! Detection of the compression formats used for NBT files.
+
+use std::io;
+
+use flate2;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use error::{DecompressReader, Result};
+
+/// The compression formats in which NBT data is commonly stored.
+///
+/// Player and level data is usually Gzip-compressed, while chunks in region
+/// files are usually zlib-compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zlib,
+}
+
+/// Guesses the compression format of NBT data from its first few bytes, which
+/// must already have been read (e.g. with `BufRead::fill_buf`), so that no
+/// data is consumed from the source itself.
+///
+/// Returns `None` if the data does not look compressed, as is the case for
+/// plain NBT, which begins with a `0x0a` tag byte. At least two bytes are
+/// needed to recognise either format.
+pub fn detect_compression(peek: &[u8]) -> Option<Compression> {
+    match *peek {
+        [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+        // A zlib header uses the deflate method (8) with a window of at most
+        // 32K, and forms a multiple of 31 when read as a big-endian u16.
+        [cmf, flg, ..] if cmf & 0x0f == 8 && cmf >> 4 <= 7 => {
+            if (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0 {
+                Some(Compression::Zlib)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Converts NBT data from one compression format to another, e.g. from Gzip to
+/// zlib, by decompressing `src` and recompressing it into `dst`.
+///
+/// The NBT data itself is copied byte for byte without being parsed, which is
+/// much faster than reading and writing a `Blob`, but also means that it is
+/// not checked for validity.
+pub fn recompress<R, W>(src: R, dst: W, from: Compression, to: Compression) -> Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut src: Box<dyn io::Read> = match from {
+        Compression::Gzip => Box::new(DecompressReader(GzDecoder::new(src))),
+        Compression::Zlib => Box::new(DecompressReader(ZlibDecoder::new(src))),
+    };
+    let level = flate2::Compression::default();
+    match to {
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(dst, level);
+            io::copy(&mut src, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(dst, level);
+            io::copy(&mut src, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(())
+}
@@ -1,24 +1,62 @@
 //! Deserialize Named Binary Tag data to a Rust data structure.
 
-use std::io;
-
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+use std::io::{self, Read};
+use std::iter;
+use std::marker::PhantomData;
+use std::mem;
+
+use byteorder::{BigEndian, ByteOrder};
 use flate2::read;
 use serde::de;
 
-use raw;
+use raw::{self, Endianness, Format};
 
-use error::{Error, Result};
+use error::{DecompressReader, Error, Result};
+use options::{count_nodes, ReadOptions, DEFAULT_MAX_DEPTH};
 
 /// Decode an object from Named Binary Tag (NBT) format.
 ///
 /// Note that only maps and structs can be decoded, because the NBT format does
 /// not support bare types. Other types will return `Error::NoRootCompound`.
+///
+/// Since mutable references to readers are readers themselves, the source can
+/// also be borrowed, e.g. as a `&mut dyn io::Read`.
 pub fn from_reader<R, T>(src: R) -> Result<T>
 where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    let mut decoder = Decoder::new(src);
+    from_reader_endian(src, Endianness::BigEndian)
+}
+
+/// Decode an object from Named Binary Tag (NBT) format, reading multi-byte
+/// values with the given byte order.
+///
+/// Note that only maps and structs can be decoded, because the NBT format does
+/// not support bare types. Other types will return `Error::NoRootCompound`.
+pub fn from_reader_endian<R, T>(src: R, endian: Endianness) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut decoder = Decoder::with_endian(src, endian);
+    de::Deserialize::deserialize(&mut decoder)
+}
+
+/// Decode an object from Named Binary Tag (NBT) format, parsing it according
+/// to the given options.
+///
+/// Note that only maps and structs can be decoded, because the NBT format does
+/// not support bare types. Other types will return `Error::NoRootCompound`.
+pub fn from_reader_with<R, T>(src: R, options: &ReadOptions) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut decoder = Decoder::with_options(src, options);
     de::Deserialize::deserialize(&mut decoder)
 }
 
@@ -32,7 +70,7 @@ where
     T: de::DeserializeOwned,
 {
     let gzip = read::GzDecoder::new(src);
-    from_reader(gzip)
+    from_reader(DecompressReader(gzip))
 }
 
 /// Decode an object from Named Binary Tag (NBT) format.
@@ -45,7 +83,191 @@ where
     T: de::DeserializeOwned,
 {
     let zlib = read::ZlibDecoder::new(src);
-    from_reader(zlib)
+    from_reader(DecompressReader(zlib))
+}
+
+/// Decode the elements of a list nested inside an NBT document one at a time,
+/// without reading the whole list into memory.
+///
+/// `path` gives the names of the compounds leading to the list, separated by
+/// dots, followed by the name of the list itself; e.g. `"Level.Entities"`.
+/// Entries before the list are skipped over, and the source is left
+/// positioned partway through the document.
+pub fn seq_from_reader<R, T>(src: R, path: &str) -> Result<ListIter<R, T>>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let mut decoder = Decoder::new(src);
+    let format = decoder.format;
+    seek_path(&mut decoder.reader, path, 0x09, "list", format)?;
+
+    let (tag, length) = raw::read_list_header(&mut decoder.reader, format)?;
+    Ok(ListIter {
+        decoder,
+        tag,
+        remaining: length,
+        marker: PhantomData,
+    })
+}
+
+/// Call `f` with each element of a `TAG_Long_Array` nested inside an NBT
+/// document, without reading the whole array into memory.
+///
+/// `path` gives the location of the array in the same form as for
+/// `seq_from_reader`; e.g. `"Level.Heightmaps.WORLD_SURFACE"`. The source is
+/// left positioned just after the array.
+pub fn for_each_long<R, F>(mut src: R, path: &str, mut f: F) -> Result<()>
+where
+    R: io::Read,
+    F: FnMut(i64),
+{
+    let endian = Endianness::BigEndian;
+    seek_path(&mut src, path, 0x0c, "long array", endian.into())?;
+
+    let length = raw::read_bare_int(&mut src, endian)?;
+    for _ in 0..length {
+        f(raw::read_bare_long(&mut src, endian)?);
+    }
+    Ok(())
+}
+
+/// Read a `TAG_List` of `TAG_Double` nested inside an NBT document, such as
+/// an entity's `Pos` or `Motion`, in one pass and without going through
+/// `serde`.
+///
+/// `path` gives the location of the list in the same form as for
+/// `seq_from_reader`. Returns `Error::TagMismatch` if the list holds another
+/// kind of element. The source is left positioned just after the list.
+pub fn read_f64_list<R>(src: R, path: &str) -> Result<Vec<f64>>
+where
+    R: io::Read,
+{
+    read_list_at(src, path, 0x06, BigEndian::read_f64)
+}
+
+/// Read a `TAG_List` of `TAG_Int` nested inside an NBT document in one
+/// pass. See `read_f64_list`.
+pub fn read_i32_list<R>(src: R, path: &str) -> Result<Vec<i32>>
+where
+    R: io::Read,
+{
+    read_list_at(src, path, 0x03, BigEndian::read_i32)
+}
+
+/// Read a `TAG_List` of `TAG_Long` nested inside an NBT document in one
+/// pass. See `read_f64_list`.
+pub fn read_i64_list<R>(src: R, path: &str) -> Result<Vec<i64>>
+where
+    R: io::Read,
+{
+    read_list_at(src, path, 0x04, BigEndian::read_i64)
+}
+
+/// Reads the payload of the list at `path` in a single read, then decodes
+/// its fixed-width elements with `decode`.
+fn read_list_at<R, T>(
+    mut src: R,
+    path: &str,
+    expected: u8,
+    decode: fn(&[u8]) -> T,
+) -> Result<Vec<T>>
+where
+    R: io::Read,
+{
+    let endian = Endianness::BigEndian;
+    seek_path(&mut src, path, 0x09, "list", endian.into())?;
+
+    let (tag, length) = raw::read_list_header(&mut src, endian)?;
+    let length = length.max(0) as usize;
+    // Empty lists may be written with any element tag, including TAG_End.
+    if tag != expected && length > 0 {
+        return Err(Error::TagMismatch(tag, expected));
+    }
+    let width = mem::size_of::<T>();
+    let size = length as u64 * width as u64;
+    // Don't trust the declared length for the allocation.
+    let mut bytes = Vec::new();
+    src.take(size).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != size {
+        return Err(Error::IncompleteNbtValue);
+    }
+    Ok(bytes.chunks_exact(width).map(decode).collect())
+}
+
+/// Read through an NBT document up to the payload of the entry at `path`,
+/// which must have the tag `expected`. `what` describes the entry in the
+/// error returned if it cannot be found.
+fn seek_path<R>(src: &mut R, path: &str, expected: u8, what: &str, format: Format) -> Result<()>
+where
+    R: io::Read,
+{
+    let (tag, _) = raw::emit_next_header(src, format)?;
+    if tag != 0x0a {
+        return Err(Error::NoRootCompound);
+    }
+
+    let mut names = path.split('.').peekable();
+    while let Some(name) = names.next() {
+        let last = names.peek().is_none();
+        loop {
+            let (tag, found) = raw::emit_next_header(src, format)?;
+            if tag == 0x00 {
+                return Err(de::Error::custom(format!(
+                    "no {} found at '{}'",
+                    what, path
+                )));
+            }
+            if found != name {
+                raw::skip_payload(src, tag, format, DEFAULT_MAX_DEPTH, &mut |_, _| ())?;
+                continue;
+            }
+            let expected = if last { expected } else { 0x0a };
+            if tag != expected {
+                return Err(Error::TagMismatch(tag, expected));
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// An iterator over the elements of an NBT list, which decodes each element
+/// as it is reached. Returned by `seq_from_reader`.
+///
+/// Iteration stops after the first error.
+pub struct ListIter<R, T> {
+    decoder: Decoder<R>,
+    tag: u8,
+    remaining: i32,
+    marker: PhantomData<T>,
+}
+
+impl<R, T> Iterator for ListIter<R, T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.remaining <= 0 {
+            return None;
+        }
+        let mut de = InnerDecoder {
+            outer: &mut self.decoder,
+            tag: self.tag,
+            hint: None,
+        };
+        let value = de::Deserialize::deserialize(&mut de);
+        self.remaining = if value.is_ok() { self.remaining - 1 } else { 0 };
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining.max(0) as usize;
+        (0, Some(remaining))
+    }
 }
 
 /// Decode objects from Named Binary Tag (NBT) format.
@@ -54,6 +276,13 @@ where
 /// not support bare types. Other types will return `Error::NoRootCompound`.
 pub struct Decoder<R> {
     reader: R,
+    format: Format,
+    depth: usize,
+    max_depth: usize,
+    nodes: usize,
+    error_paths: bool,
+    hints: HashMap<String, u8>,
+    path: String,
 }
 
 impl<R> Decoder<R>
@@ -62,20 +291,97 @@ where
 {
     /// Create an NBT Decoder from a given `io::Read` source.
     pub fn new(src: R) -> Self {
-        Decoder { reader: src }
+        Decoder::with_endian(src, Endianness::BigEndian)
+    }
+
+    /// Create an NBT Decoder from a given `io::Read` source, which reads
+    /// multi-byte values with the given byte order.
+    pub fn with_endian(src: R, endian: Endianness) -> Self {
+        Decoder {
+            reader: src,
+            format: endian.into(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            nodes: usize::MAX,
+            error_paths: false,
+            hints: HashMap::new(),
+            path: String::new(),
+        }
+    }
+
+    /// Create an NBT Decoder from a given `io::Read` source, which parses it
+    /// according to the given options.
+    pub fn with_options(src: R, options: &ReadOptions) -> Self {
+        Decoder {
+            reader: src,
+            format: options.format(),
+            depth: 0,
+            max_depth: options.max_depth,
+            nodes: options.max_nodes,
+            error_paths: options.error_paths,
+            hints: options.type_hints.clone(),
+            path: String::new(),
+        }
+    }
+
+    /// Appends `segment` to the path of the value being decoded, returning
+    /// the length to restore with `leave`. The path is only tracked when
+    /// errors should record it or there are type hints to look up.
+    fn enter<S>(&mut self, segment: S) -> usize
+    where
+        S: fmt::Display,
+    {
+        let len = self.path.len();
+        if !self.error_paths && self.hints.is_empty() {
+            return len;
+        }
+        if len > 0 {
+            self.path.push('.');
+        }
+        // Writing to a `String` cannot fail.
+        write!(self.path, "{}", segment).unwrap();
+        len
+    }
+
+    /// Restores the path saved by `enter`, after first recording it in an
+    /// error from the value being left, unless a nested value already did.
+    fn leave<T>(&mut self, len: usize, result: Result<T>) -> Result<T> {
+        let result = match result {
+            // An error from a nested value already has the longer path.
+            Err(Error::AtPath(path, e)) => Err(Error::AtPath(path, e)),
+            Err(e) if self.error_paths => Err(Error::AtPath(self.path.clone(), Box::new(e))),
+            result => result,
+        };
+        self.path.truncate(len);
+        result
+    }
+
+    /// Runs `f` one level of compound or list nesting deeper, failing if this
+    /// would exceed the depth limit.
+    fn nested<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        if self.depth >= self.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
     }
 }
 
 impl<'de: 'a, 'a, R: io::Read> de::Deserializer<'de> for &'a mut Decoder<R> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        // The decoder cannot deserialize types by default. It can only handle
-        // maps and structs.
-        Err(Error::NoRootCompound)
+        // The root is always a compound, so self-describing types such as
+        // `serde_json::Value` can be deserialized from it as a map.
+        self.deserialize_map(visitor)
     }
 
     fn deserialize_struct<V>(
@@ -110,10 +416,13 @@ impl<'de: 'a, 'a, R: io::Read> de::Deserializer<'de> for &'a mut Decoder<R> {
         V: de::Visitor<'de>,
     {
         // Ignore the header (if there is one).
-        let (tag, _) = raw::emit_next_header(&mut self.reader)?;
+        let (tag, _) = raw::emit_next_header(&mut self.reader, self.format)?;
 
         match tag {
-            0x0a => visitor.visit_map(MapDecoder::new(self)),
+            0x0a => {
+                count_nodes(&mut self.nodes, 1)?;
+                self.nested(|de| visitor.visit_map(MapDecoder::new(de)))
+            }
             _ => Err(Error::NoRootCompound),
         }
     }
@@ -128,6 +437,7 @@ impl<'de: 'a, 'a, R: io::Read> de::Deserializer<'de> for &'a mut Decoder<R> {
 struct MapDecoder<'a, R: io::Read + 'a> {
     outer: &'a mut Decoder<R>,
     tag: Option<u8>,
+    key: String,
 }
 
 impl<'a, R> MapDecoder<'a, R>
@@ -135,7 +445,11 @@ where
     R: io::Read,
 {
     fn new(outer: &'a mut Decoder<R>) -> Self {
-        MapDecoder { outer, tag: None }
+        MapDecoder {
+            outer,
+            tag: None,
+            key: String::new(),
+        }
     }
 }
 
@@ -152,31 +466,35 @@ impl<'de: 'a, 'a, R: io::Read + 'a> de::MapAccess<'de> for MapDecoder<'a, R> {
         if tag == 0x00 {
             return Ok(None);
         }
+        count_nodes(&mut self.outer.nodes, 1)?;
 
         // Keep track of the tag so that we can decode the field correctly.
         self.tag = Some(tag as u8);
 
-        // TODO: Enforce that keys must be String. This is a bit of a hack.
-        let mut de = InnerDecoder {
-            outer: self.outer,
-            tag: 0x08,
-        };
+        // Keys are always strings. Keep the key so that errors in the value
+        // can report where they happened.
+        self.key = raw::read_bare_string(&mut self.outer.reader, self.outer.format)?;
+        let de = de::IntoDeserializer::<Error>::into_deserializer(self.key.as_str());
 
-        Ok(Some(seed.deserialize(&mut de)?))
+        Ok(Some(seed.deserialize(de)?))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        let mut de = match self.tag {
-            Some(tag) => InnerDecoder {
-                outer: self.outer,
-                tag,
-            },
+        let tag = match self.tag {
+            Some(tag) => tag,
             None => unimplemented!(),
         };
-        Ok(seed.deserialize(&mut de)?)
+        let len = self.outer.enter(&self.key);
+        let hint = self.outer.hints.get(&self.outer.path).cloned();
+        let value = seed.deserialize(&mut InnerDecoder {
+            outer: self.outer,
+            tag,
+            hint,
+        });
+        self.outer.leave(len, value)
     }
 }
 
@@ -184,6 +502,7 @@ impl<'de: 'a, 'a, R: io::Read + 'a> de::MapAccess<'de> for MapDecoder<'a, R> {
 struct SeqDecoder<'a, R: io::Read + 'a> {
     outer: &'a mut Decoder<R>,
     tag: u8,
+    hint: Option<u8>,
     length: i32,
     current: i32,
 }
@@ -193,41 +512,48 @@ where
     R: io::Read,
 {
     fn list(outer: &'a mut Decoder<R>) -> Result<Self> {
-        let tag = raw::read_bare_byte(&mut outer.reader)?;
-        let length = raw::read_bare_int(&mut outer.reader)?;
+        let (tag, length) = raw::read_list_header(&mut outer.reader, outer.format)?;
+        count_nodes(&mut outer.nodes, length.max(0) as usize)?;
+        // The elements of a list all have the same type, so they share the
+        // hint given for the list itself.
+        let hint = outer.hints.get(&outer.path).cloned();
         Ok(SeqDecoder {
             outer,
-            tag: tag as u8,
+            tag,
+            hint,
             length,
             current: 0,
         })
     }
 
     fn byte_array(outer: &'a mut Decoder<R>) -> Result<Self> {
-        let length = raw::read_bare_int(&mut outer.reader)?;
+        let length = raw::read_bare_int(&mut outer.reader, outer.format)?;
         Ok(SeqDecoder {
             outer,
             tag: 0x01,
+            hint: None,
             length,
             current: 0,
         })
     }
 
     fn int_array(outer: &'a mut Decoder<R>) -> Result<Self> {
-        let length = raw::read_bare_int(&mut outer.reader)?;
+        let length = raw::read_bare_int(&mut outer.reader, outer.format)?;
         Ok(SeqDecoder {
             outer,
             tag: 0x03,
+            hint: None,
             length,
             current: 0,
         })
     }
 
     fn long_array(outer: &'a mut Decoder<R>) -> Result<Self> {
-        let length = raw::read_bare_int(&mut outer.reader)?;
+        let length = raw::read_bare_int(&mut outer.reader, outer.format)?;
         Ok(SeqDecoder {
             outer,
             tag: 0x04,
+            hint: None,
             length,
             current: 0,
         })
@@ -245,20 +571,110 @@ impl<'de: 'a, 'a, R: io::Read + 'a> de::SeqAccess<'de> for SeqDecoder<'a, R> {
             return Ok(None);
         }
 
-        let mut de = InnerDecoder {
+        let len = self.outer.enter(self.current);
+        let value = seed.deserialize(&mut InnerDecoder {
             outer: self.outer,
             tag: self.tag,
+            hint: self.hint,
+        });
+        let value = match value {
+            // Report how far through the list we got, rather than just that
+            // the data ended.
+            Err(Error::IncompleteNbtValue) => Err(Error::IncompleteList(self.current, self.length)),
+            value => value,
         };
-        let value = seed.deserialize(&mut de)?;
+        let value = self.outer.leave(len, value)?;
 
         self.current += 1;
 
         Ok(Some(value))
     }
 
-    /// We always know the length of an NBT list in advance.
+    /// We always know the length of an NBT list in advance. Report the number
+    /// of elements still to come, and never a "negative" length.
     fn size_hint(&self) -> Option<usize> {
-        Some(self.length as usize)
+        Some((self.length - self.current).max(0) as usize)
+    }
+}
+
+/// Decoder for enum variants that carry data, which are stored as a
+/// `Tag_Compound` with a single entry.
+struct EnumDecoder<'a, R: io::Read + 'a> {
+    outer: &'a mut Decoder<R>,
+    tag: u8,
+}
+
+impl<'a, R> EnumDecoder<'a, R>
+where
+    R: io::Read,
+{
+    fn new(outer: &'a mut Decoder<R>) -> Self {
+        EnumDecoder { outer, tag: 0x00 }
+    }
+
+    /// Consume the end tag of the enclosing compound, which must not have any
+    /// other entries.
+    fn end(&mut self) -> Result<()> {
+        match raw::emit_next_header(&mut self.outer.reader, self.outer.format)? {
+            (0x00, _) => Ok(()),
+            (_, name) => Err(Error::UnexpectedField(name)),
+        }
+    }
+}
+
+impl<'de: 'a, 'a, R: io::Read + 'a> de::EnumAccess<'de> for EnumDecoder<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let (tag, name) = raw::emit_next_header(&mut self.outer.reader, self.outer.format)?;
+        if tag == 0x00 {
+            return Err(Error::IncompleteNbtValue);
+        }
+        self.tag = tag;
+        let variant = seed.deserialize(de::IntoDeserializer::<Error>::into_deserializer(name))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de: 'a, 'a, R: io::Read + 'a> de::VariantAccess<'de> for EnumDecoder<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(Error::TagMismatch(0x0a, 0x08))
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let value = {
+            let mut de = InnerDecoder {
+                outer: self.outer,
+                tag: self.tag,
+                hint: None,
+            };
+            seed.deserialize(&mut de)?
+        };
+        self.end()?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::UnrepresentableType("tuple variant"))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::UnrepresentableType("struct variant"))
     }
 }
 
@@ -266,6 +682,79 @@ impl<'de: 'a, 'a, R: io::Read + 'a> de::SeqAccess<'de> for SeqDecoder<'a, R> {
 struct InnerDecoder<'a, R: io::Read + 'a> {
     outer: &'a mut Decoder<R>,
     tag: u8,
+    hint: Option<u8>,
+}
+
+impl<'a, R: io::Read> InnerDecoder<'a, R> {
+    fn read_u128(&mut self) -> Result<u128> {
+        if self.tag != 0x0b {
+            return Err(Error::TagMismatch(self.tag, 0x0b));
+        }
+        let outer = &mut self.outer;
+        let words = raw::read_bare_int_array(&mut outer.reader, outer.format)?;
+        if words.len() != 4 {
+            return Err(de::Error::invalid_length(
+                words.len(),
+                &"an int array of length 4",
+            ));
+        }
+        Ok(words
+            .iter()
+            .fold(0, |acc, &word| (acc << 32) | u128::from(word as u32)))
+    }
+
+    /// Visits an array for `nbt::Value`: byte arrays as byte buffers, and int
+    /// and long arrays as a map with a single entry keyed by their magic name.
+    fn visit_value_array<'de, V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let outer = &mut self.outer;
+        match self.tag {
+            0x07 => visitor.visit_byte_buf(raw::read_bare_bytes(&mut outer.reader, outer.format)?),
+            0x0b => {
+                let vals = raw::read_bare_int_array(&mut outer.reader, outer.format)?;
+                let entry = iter::once(("__hematite_nbt_i32_array__", vals));
+                visitor.visit_map(de::value::MapDeserializer::new(entry))
+            }
+            0x0c => {
+                let vals = raw::read_bare_long_array(&mut outer.reader, outer.format)?;
+                let entry = iter::once(("__hematite_nbt_i64_array__", vals));
+                visitor.visit_map(de::value::MapDeserializer::new(entry))
+            }
+            t => Err(Error::TagMismatch(t, 0x07)),
+        }
+    }
+
+    /// Returns the integer tag ID hinted for this value, if it is an integer
+    /// stored with a different width.
+    fn int_hint(&self) -> Option<u8> {
+        match (self.tag, self.hint) {
+            (0x01..=0x04, Some(hint @ 0x01..=0x04)) if hint != self.tag => Some(hint),
+            _ => None,
+        }
+    }
+
+    /// Reads an integer and visits it as the hinted integer type.
+    fn visit_hinted_int<'de, V>(&mut self, hint: u8, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let outer = &mut self.outer;
+        let value = match self.tag {
+            0x01 => i64::from(raw::read_bare_byte(&mut outer.reader)?),
+            0x02 => i64::from(raw::read_bare_short(&mut outer.reader, outer.format)?),
+            0x03 => i64::from(raw::read_bare_int(&mut outer.reader, outer.format)?),
+            _ => raw::read_bare_long(&mut outer.reader, outer.format)?,
+        };
+        let mismatch = Error::TagMismatch(self.tag, hint);
+        match hint {
+            0x01 => visitor.visit_i8(i8::try_from(value).map_err(|_| mismatch)?),
+            0x02 => visitor.visit_i16(i16::try_from(value).map_err(|_| mismatch)?),
+            0x03 => visitor.visit_i32(i32::try_from(value).map_err(|_| mismatch)?),
+            _ => visitor.visit_i64(value),
+        }
+    }
 }
 
 impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecoder<'a, R> {
@@ -275,19 +764,22 @@ impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecode
     where
         V: de::Visitor<'de>,
     {
+        if let Some(hint) = self.int_hint() {
+            return self.visit_hinted_int(hint, visitor);
+        }
         let outer = &mut self.outer;
 
         match self.tag {
             0x01 => visitor.visit_i8(raw::read_bare_byte(&mut outer.reader)?),
-            0x02 => visitor.visit_i16(raw::read_bare_short(&mut outer.reader)?),
-            0x03 => visitor.visit_i32(raw::read_bare_int(&mut outer.reader)?),
-            0x04 => visitor.visit_i64(raw::read_bare_long(&mut outer.reader)?),
-            0x05 => visitor.visit_f32(raw::read_bare_float(&mut outer.reader)?),
-            0x06 => visitor.visit_f64(raw::read_bare_double(&mut outer.reader)?),
+            0x02 => visitor.visit_i16(raw::read_bare_short(&mut outer.reader, outer.format)?),
+            0x03 => visitor.visit_i32(raw::read_bare_int(&mut outer.reader, outer.format)?),
+            0x04 => visitor.visit_i64(raw::read_bare_long(&mut outer.reader, outer.format)?),
+            0x05 => visitor.visit_f32(raw::read_bare_float(&mut outer.reader, outer.format)?),
+            0x06 => visitor.visit_f64(raw::read_bare_double(&mut outer.reader, outer.format)?),
             0x07 => visitor.visit_seq(SeqDecoder::byte_array(outer)?),
-            0x08 => visitor.visit_string(raw::read_bare_string(&mut outer.reader)?),
-            0x09 => visitor.visit_seq(SeqDecoder::list(outer)?),
-            0x0a => visitor.visit_map(MapDecoder::new(outer)),
+            0x08 => visitor.visit_string(raw::read_bare_string(&mut outer.reader, outer.format)?),
+            0x09 => outer.nested(|de| visitor.visit_seq(SeqDecoder::list(de)?)),
+            0x0a => outer.nested(|de| visitor.visit_map(MapDecoder::new(de))),
             0x0b => visitor.visit_seq(SeqDecoder::int_array(outer)?),
             0x0c => visitor.visit_seq(SeqDecoder::long_array(outer)?),
             t => Err(Error::InvalidTypeId(t)),
@@ -313,6 +805,72 @@ impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecode
         }
     }
 
+    /// Deserialize byte buffers from a `Tag_ByteArray` in a single read.
+    /// Other tags (e.g. a `Tag_List` of bytes) are left to the visitor.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.tag {
+            0x07 => {
+                let outer = &mut self.outer;
+                visitor.visit_byte_buf(raw::read_bare_bytes(&mut outer.reader, outer.format)?)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /// Deserialize 128-bit integers, such as UUIDs, from a `Tag_IntArray` of
+    /// four integers, most significant first.
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i128(self.read_u128()? as i128)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u128(self.read_u128()?)
+    }
+
+    /// Deserialize unit variants from their names, and newtype variants from
+    /// a `Tag_Compound` with a single entry keyed by the variant name.
+    ///
+    /// Internally tagged enums, such as items selected by their `id`, do not
+    /// come through here: serde reads the whole compound with
+    /// `deserialize_any` and then picks the variant. This loses the hint that
+    /// a byte is a boolean, so the fields of such variants cannot be `bool`.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let outer = &mut self.outer;
+
+        match self.tag {
+            0x08 => {
+                let variant = raw::read_bare_string(&mut outer.reader, outer.format)?;
+                visitor.visit_enum(de::IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            0x0a => outer.nested(|de| visitor.visit_enum(EnumDecoder::new(de))),
+            t => Err(Error::TagMismatch(t, 0x0a)),
+        }
+    }
+
     /// Interpret missing values as None.
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
@@ -336,15 +894,34 @@ impl<'a, 'b: 'a, 'de, R: io::Read> de::Deserializer<'de> for &'b mut InnerDecode
     }
 
     /// Deserialize newtype structs by their underlying types.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    ///
+    /// `nbt::Value` asks for itself under a magic name, so that arrays can be
+    /// told apart from lists.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        match (name, self.tag) {
+            ("__hematite_nbt_value__", 0x07)
+            | ("__hematite_nbt_value__", 0x0b)
+            | ("__hematite_nbt_value__", 0x0c) => self.visit_value_array(visitor),
+            // Lists and compounds are visited here rather than through
+            // `deserialize_any`, to keep deeply nested values within the stack.
+            ("__hematite_nbt_value__", 0x09) => {
+                let outer = &mut self.outer;
+                outer.nested(|de| visitor.visit_seq(SeqDecoder::list(de)?))
+            }
+            ("__hematite_nbt_value__", 0x0a) => {
+                let outer = &mut self.outer;
+                outer.nested(|de| visitor.visit_map(MapDecoder::new(de)))
+            }
+            ("__hematite_nbt_value__", _) => self.deserialize_any(visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
     }
 
     forward_to_deserialize_any! {
-        u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes byte_buf seq
-        map tuple_struct struct tuple enum identifier ignored_any
+        u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string seq
+        map tuple_struct struct tuple identifier ignored_any
     }
 }
@@ -1,9 +1,11 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
-use std::io::ErrorKind::InvalidInput;
+use std::io::ErrorKind::{self, InvalidInput};
 use std::result::Result as StdResult;
 
+use value::tag_name_for_id;
+
 #[cfg(feature = "serde")]
 use serde;
 
@@ -37,6 +39,10 @@ pub enum Error {
     /// An error for when NBT binary representations are missing end tags,
     /// contain fewer bytes than advertised, or are otherwise incomplete.
     IncompleteNbtValue,
+    /// An error for when the data ends partway through a list or array.
+    /// Includes the number of elements successfully read, followed by the
+    /// number of elements the list declared.
+    IncompleteList(i32, i32),
     /// An error encountered when parsing NBT binary representations, where
     /// deserialization encounters a different tag than expected.
     TagMismatch(u8, u8),
@@ -51,6 +57,41 @@ pub enum Error {
     /// An error encountered when trying to (de)serialize a map key with a
     /// non-string type.
     NonStringMapKey,
+    /// An error encountered when an arithmetic update to an integer value would
+    /// not fit in the range of its tag type.
+    Overflow,
+    /// An error encountered when decompressing Gzip or zlib data, e.g.
+    /// because the compressed stream is corrupt or truncated.
+    Decompression(String),
+    /// An error for when a string, list, or array is too long for its length
+    /// to be written in the NBT format. Includes the name of the tag type.
+    LengthOverflow(&'static str),
+    /// An error encountered when parsing NBT binary representations, where
+    /// compounds and lists are nested more deeply than the configured limit.
+    DepthLimitExceeded,
+    /// An error encountered when parsing NBT binary representations, where
+    /// more tags are read than the configured limit.
+    SizeLimitExceeded,
+    /// An error for when no value exists at a path within a `Blob`. Includes
+    /// the path in question.
+    PathNotFound(String),
+    /// An error for when the root compound of NBT data does not have the
+    /// expected name, e.g. because the wrong kind of file was read. Includes
+    /// the names found and expected, boxed to keep `Error` small.
+    UnexpectedRootName(Box<RootNames>),
+    /// An error for when NBT binary representations end partway through the
+    /// name of the root compound, which usually means that the data is not
+    /// NBT at all, or is still compressed.
+    IncompleteRootName,
+    /// An error for when a VarInt in network NBT data has more continuation
+    /// bytes than its type allows. Includes the maximum number of bytes.
+    VarIntTooLong(usize),
+    /// An error encountered when parsing stringified NBT (SNBT). Includes a
+    /// description of the problem and where in the input it was found.
+    SnbtParse(String),
+    /// Wraps an error encountered when deserializing the value at a dotted
+    /// path, made of compound keys and list indices, within NBT data.
+    AtPath(String, Box<Error>),
 }
 
 impl fmt::Display for Error {
@@ -59,13 +100,21 @@ impl fmt::Display for Error {
             &Error::IoError(ref e) => e.fmt(f),
             #[cfg(feature = "serde")]
             &Error::Serde(ref msg) => write!(f, "{}", msg),
-            &Error::InvalidTypeId(t) => write!(f, "invalid NBT tag byte: '{}'", t),
+            &Error::InvalidTypeId(t) => write!(f, "invalid NBT tag byte: {}", TagId(t)),
             Error::HeterogeneousList => write!(f, "values in NBT Lists must be homogeneous"),
             Error::NoRootCompound => write!(f, "the root value must be Compound-like (tag = 0x0a)"),
             Error::InvalidUtf8 => write!(f, "a string is not valid UTF-8"),
             Error::IncompleteNbtValue => write!(f, "data does not represent a complete NbtValue"),
+            &Error::IncompleteList(read, expected) => {
+                write!(f, "data ended after {} of {} list elements", read, expected)
+            }
             &Error::TagMismatch(a, b) => {
-                write!(f, "encountered NBT tag '{}' but expected '{}'", a, b)
+                write!(
+                    f,
+                    "encountered NBT tag {} but expected {}",
+                    TagId(a),
+                    TagId(b)
+                )
             }
             &Error::NonBooleanByte(b) => {
                 write!(f, "encountered a byte value '{}' inside a boolean", b)
@@ -79,6 +128,45 @@ impl fmt::Display for Error {
                 name
             ),
             Error::NonStringMapKey => write!(f, "encountered a non-string map key"),
+            Error::Overflow => write!(f, "integer value out of range for its tag type"),
+            Error::Decompression(msg) => write!(f, "failed to decompress data: {}", msg),
+            &Error::LengthOverflow(name) => write!(f, "{} is too long to represent in NBT", name),
+            Error::DepthLimitExceeded => write!(f, "compounds and lists are nested too deeply"),
+            Error::SizeLimitExceeded => write!(f, "data contains too many tags"),
+            Error::PathNotFound(path) => write!(f, "no value found at '{}'", path),
+            Error::AtPath(path, e) => write!(f, "{} at {}", e, path),
+            Error::IncompleteRootName => write!(
+                f,
+                "data ended within the name of the root compound; is it compressed or corrupt?"
+            ),
+            Error::VarIntTooLong(max) => write!(f, "a VarInt is longer than {} bytes", max),
+            Error::SnbtParse(msg) => write!(f, "invalid SNBT: {}", msg),
+            Error::UnexpectedRootName(names) => write!(
+                f,
+                "root compound is named '{}' but expected '{}'",
+                names.found, names.expected
+            ),
+        }
+    }
+}
+
+/// The names involved in an `Error::UnexpectedRootName`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootNames {
+    /// The name of the root compound that was read.
+    pub found: String,
+    /// The name that the root compound was expected to have.
+    pub expected: String,
+}
+
+/// Displays a tag ID along with its name, if it is valid.
+struct TagId(u8);
+
+impl fmt::Display for TagId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match tag_name_for_id(self.0) {
+            Some(name) => write!(f, "'{}' ({})", self.0, name),
+            None => write!(f, "'{}'", self.0),
         }
     }
 }
@@ -87,6 +175,7 @@ impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
             Error::IoError(ref e) => e.source(),
+            Error::AtPath(_, ref e) => Some(&**e),
             _ => None,
         }
     }
@@ -96,8 +185,11 @@ impl StdError for Error {
 impl PartialEq<Error> for Error {
     fn eq(&self, other: &Error) -> bool {
         use Error::{
-            HeterogeneousList, IncompleteNbtValue, InvalidTypeId, InvalidUtf8, IoError,
-            NoRootCompound, NonBooleanByte, TagMismatch, UnexpectedField, UnrepresentableType,
+            AtPath, Decompression, DepthLimitExceeded, HeterogeneousList, IncompleteList,
+            IncompleteNbtValue, IncompleteRootName, InvalidTypeId, InvalidUtf8, IoError,
+            LengthOverflow, NoRootCompound, NonBooleanByte, Overflow, PathNotFound,
+            SizeLimitExceeded, SnbtParse, TagMismatch, UnexpectedField, UnexpectedRootName,
+            UnrepresentableType, VarIntTooLong,
         };
 
         match (self, other) {
@@ -109,10 +201,22 @@ impl PartialEq<Error> for Error {
             (&NoRootCompound, &NoRootCompound) => true,
             (&InvalidUtf8, &InvalidUtf8) => true,
             (&IncompleteNbtValue, &IncompleteNbtValue) => true,
+            (&IncompleteList(a, b), &IncompleteList(c, d)) => a == c && b == d,
             (&TagMismatch(a, b), &TagMismatch(c, d)) => a == c && b == d,
             (&UnexpectedField(ref a), &UnexpectedField(ref b)) => a == b,
             (&NonBooleanByte(a), &NonBooleanByte(b)) => a == b,
             (&UnrepresentableType(ref a), &UnrepresentableType(ref b)) => a == b,
+            (&Overflow, &Overflow) => true,
+            (Decompression(a), Decompression(b)) => a == b,
+            (&LengthOverflow(a), &LengthOverflow(b)) => a == b,
+            (&DepthLimitExceeded, &DepthLimitExceeded) => true,
+            (&SizeLimitExceeded, &SizeLimitExceeded) => true,
+            (PathNotFound(a), PathNotFound(b)) => a == b,
+            (AtPath(a, e), AtPath(b, f)) => a == b && e == f,
+            (IncompleteRootName, IncompleteRootName) => true,
+            (VarIntTooLong(a), VarIntTooLong(b)) => a == b,
+            (SnbtParse(a), SnbtParse(b)) => a == b,
+            (UnexpectedRootName(a), UnexpectedRootName(b)) => a == b,
             _ => false,
         }
     }
@@ -120,8 +224,15 @@ impl PartialEq<Error> for Error {
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
-        use std::io::ErrorKind;
-
+        // Unwrap errors that were passed through an `io::Read` adapter.
+        let wrapped = match e.get_ref() {
+            Some(inner) => inner.is::<Error>(),
+            None => false,
+        };
+        if wrapped {
+            let inner = e.into_inner().unwrap();
+            return *inner.downcast::<Error>().unwrap();
+        }
         if e.kind() == ErrorKind::UnexpectedEof {
             return Error::IncompleteNbtValue;
         }
@@ -129,6 +240,30 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Wraps a decompressing reader so that any failure to decompress is reported
+/// as `Error::Decompression`, rather than e.g. being mistaken for a short NBT
+/// body when the compressed stream is truncated. Other errors, such as those
+/// of the underlying source, are passed through unchanged.
+pub(crate) struct DecompressReader<R>(pub R);
+
+impl<R> io::Read for DecompressReader<R>
+where
+    R: io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(|e| match e.kind() {
+            // flate2 reports a corrupt stream as invalid input or data, and a
+            // truncated one as an unexpected EOF.
+            kind @ ErrorKind::InvalidInput
+            | kind @ ErrorKind::InvalidData
+            | kind @ ErrorKind::UnexpectedEof => {
+                io::Error::new(kind, Error::Decompression(e.to_string()))
+            }
+            _ => e,
+        })
+    }
+}
+
 impl From<cesu8::Cesu8DecodingError> for Error {
     fn from(_: cesu8::Cesu8DecodingError) -> Error {
         Error::InvalidUtf8
@@ -5,9 +5,14 @@ extern crate cesu8;
 extern crate flate2;
 
 /* Re-export the core API from submodules. */
-pub use blob::Blob;
-pub use error::{Error, Result};
-pub use value::Value;
+pub use annotate::annotate;
+pub use bits::LongArrayBits;
+pub use blob::{Blob, BlobStats};
+pub use compression::{detect_compression, recompress, Compression};
+pub use error::{Error, Result, RootNames};
+pub use options::ReadOptions;
+pub use raw::{Endianness, Flavor, StringEncoding};
+pub use value::{tag_name_for_id, Value};
 
 #[cfg(feature = "preserve_order")]
 extern crate indexmap;
@@ -18,22 +23,32 @@ pub use std::collections::HashMap as Map;
 
 #[cfg(feature = "serde")]
 #[doc(inline)]
-pub use de::{from_gzip_reader, from_reader, from_zlib_reader};
+pub use de::{
+    from_gzip_reader, from_reader, from_reader_endian, from_reader_with, from_zlib_reader,
+};
 #[cfg(feature = "serde")]
 #[doc(inline)]
 pub use ser::{i32_array, i64_array, i8_array};
 #[cfg(feature = "serde")]
 #[doc(inline)]
-pub use ser::{to_gzip_writer, to_writer, to_zlib_writer};
+pub use ser::{to_gzip_writer, to_writer, to_writer_endian, to_zlib_writer};
 
+mod annotate;
+mod bits;
 mod blob;
+pub mod chunk;
+mod compression;
 mod error;
-mod raw;
+mod options;
+pub mod raw;
+mod snbt;
 mod value;
 
 #[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
 
 #[cfg(feature = "serde")]
 #[macro_use]
@@ -41,6 +56,8 @@ mod macros;
 #[cfg(feature = "serde")]
 pub mod de;
 #[cfg(feature = "serde")]
+pub mod list_of;
+#[cfg(feature = "serde")]
 pub mod ser;
 
 #[cfg(test)]
@@ -0,0 +1,64 @@
+//! Serde helpers for fields holding a list of compounds.
+//!
+//! NBT lists are homogeneous, so a `Vec<T>` where `T` is itself a struct is
+//! written as a `TAG_List` whose elements are each a `TAG_Compound`. This is
+//! the layout used by e.g. the `servers` list in `servers.dat`, where each
+//! entry is a compound holding the server's `name` and `ip`.
+//!
+//! The functions in this module can be used with serde's field annotation
+//! `with`, and are a starting point for handling nested structures that need
+//! custom per-element treatment:
+//!
+//! ```
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate nbt;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Server {
+//!     name: String,
+//!     ip: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct ServerList {
+//!     #[serde(with = "nbt::list_of")]
+//!     servers: Vec<Server>,
+//! }
+//!
+//! # fn main() {
+//! let list = ServerList {
+//!     servers: vec![Server {
+//!         name: "Home".to_string(),
+//!         ip: "127.0.0.1:25565".to_string(),
+//!     }],
+//! };
+//!
+//! let mut serialized = Vec::new();
+//! nbt::to_writer(&mut serialized, &list, None).unwrap();
+//! let read: ServerList = nbt::from_reader(&serialized[..]).unwrap();
+//! assert_eq!(read, list);
+//! # }
+//! ```
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Serializes a sequence of values as a `TAG_List`.
+pub fn serialize<I, S>(list: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(list)
+}
+
+/// Deserializes a `TAG_List` into a `Vec`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Vec::deserialize(deserializer)
+}
@@ -173,6 +173,20 @@ macro_rules! unrepresentable {
     };
 }
 
+/// Implement map key serializer methods for each `$func($type)`, writing the
+/// value in its `Display` form if the encoder stringifies keys, and returning
+/// `Error::NonStringMapKey` otherwise.
+macro_rules! stringify_map_keys {
+    ($($func:ident($type:ty))*) => {
+        $(
+            #[inline]
+            fn $func(self, value: $type) -> Result<()> {
+                self.serialize_display(value)
+            }
+        )*
+    };
+}
+
 /// Serde `serialize_with` implementation for array serialization.
 ///
 /// This macro provides the function body for `i8_array`, `i32_array` and `i64_array`
@@ -0,0 +1,99 @@
+//! Options controlling how NBT data is parsed.
+
+use std::collections::HashMap;
+
+use error::{Error, Result};
+use raw::{Endianness, Flavor, Format};
+
+/// Options for reading NBT data, for use with `Blob::from_reader_with` and
+/// `de::from_reader_with`.
+///
+/// `ReadOptions::default()` matches the behaviour of the plain `from_reader`
+/// functions: big-endian data is expected, nesting is limited to 512 levels,
+/// and any malformed input is rejected. Options can be changed by setting
+/// fields on the default value:
+///
+/// ```
+/// use nbt::{Endianness, ReadOptions};
+///
+/// let mut options = ReadOptions::default();
+/// options.endian = Endianness::LittleEndian;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReadOptions {
+    /// The byte order of multi-byte values.
+    pub endian: Endianness,
+    /// How integers and lengths are encoded, e.g. `Flavor::VarInt` for the
+    /// Bedrock Edition network protocol.
+    pub flavor: Flavor,
+    /// The maximum number of compounds and lists that may be nested inside
+    /// one another, counting the root compound. Deeper data is rejected with
+    /// `Error::DepthLimitExceeded`, rather than overflowing the stack.
+    pub max_depth: usize,
+    /// The maximum number of tags that may be read, counting the root
+    /// compound, every entry of a compound, and every element of a list (but
+    /// not the elements of arrays). More tags are rejected with
+    /// `Error::SizeLimitExceeded`. Unlimited by default.
+    pub max_nodes: usize,
+    /// Whether errors when deserializing with `serde` should record where in
+    /// the data they happened, by wrapping them in `Error::AtPath`, e.g. at
+    /// `"Data.Player.abilities.flySpeed"`. Off by default, so that the
+    /// original errors are returned unchanged.
+    pub error_paths: bool,
+    /// The expected tag IDs of integer values when deserializing with
+    /// `serde`, keyed by their dotted paths from the root compound, e.g.
+    /// `"Inventory.0.Count"`. Empty by default.
+    ///
+    /// Minecraft does not always store a field with the same integer width,
+    /// which self-describing targets such as `Value` would otherwise preserve.
+    /// A hinted `TAG_Byte`, `TAG_Short`, `TAG_Int` or `TAG_Long` is decoded
+    /// as though it had the hinted integer type instead, failing with
+    /// `Error::TagMismatch` if its value does not fit. Hints for other types
+    /// are ignored. The elements of a list all have the same type, so they
+    /// are hinted by the path of the list, e.g. `"Pos"`, rather than by their
+    /// own paths.
+    pub type_hints: HashMap<String, u8>,
+    /// Whether data which ends where the `TAG_End` closing the root compound
+    /// is expected should be accepted, keeping every entry read, rather than
+    /// rejected with `Error::IncompleteNbtValue`. This can recover truncated
+    /// files. Off by default, and only used by `Blob::from_reader_with`.
+    pub tolerate_missing_end: bool,
+}
+
+impl ReadOptions {
+    /// The byte order and flavor to read with.
+    pub(crate) fn format(&self) -> Format {
+        Format {
+            endian: self.endian,
+            flavor: self.flavor,
+        }
+    }
+}
+
+/// The nesting limit used by default, which matches Minecraft's own.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 512;
+
+impl Default for ReadOptions {
+    fn default() -> ReadOptions {
+        ReadOptions {
+            endian: Endianness::default(),
+            flavor: Flavor::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_nodes: usize::MAX,
+            error_paths: false,
+            type_hints: HashMap::new(),
+            tolerate_missing_end: false,
+        }
+    }
+}
+
+/// Reduces the number of tags which may still be read by `count`, failing if
+/// there are not that many left.
+pub(crate) fn count_nodes(remaining: &mut usize, count: usize) -> Result<()> {
+    if count > *remaining {
+        return Err(Error::SizeLimitExceeded);
+    }
+    *remaining -= count;
+    Ok(())
+}
@@ -1,19 +1,125 @@
 //! Primitive functions for serializing and deserializing NBT data.
 
-use std::io;
+use std::io::{self, Read};
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use cesu8::{from_java_cesu8, to_java_cesu8};
 
 use error::{Error, Result};
 
+/// The text encoding of string data.
+///
+/// NBT strings are stored as Java's modified UTF-8 (CESU-8), which differs
+/// from standard UTF-8 only in how it encodes NUL and characters outside the
+/// Basic Multilingual Plane. Text stashed elsewhere, such as in a
+/// `TAG_Byte_Array`, may use either.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// Standard UTF-8, rejecting CESU-8 surrogate pairs.
+    Utf8,
+    /// Java's modified UTF-8, also accepting standard UTF-8.
+    #[default]
+    Cesu8,
+}
+
+/// The byte order used for the multi-byte values in NBT data.
+///
+/// Java Edition uses big-endian data throughout, while Bedrock Edition stores
+/// its files in little-endian order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+
+/// How integers and lengths are encoded in NBT data, independently of byte
+/// order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Flavor {
+    /// Fixed-width integers and lengths, as in files.
+    #[default]
+    Fixed,
+    /// `TAG_Int` and `TAG_Long` values and the lengths of lists and arrays
+    /// are zigzag-encoded VarInts, and the lengths of strings are unsigned
+    /// VarInts, as in the Bedrock Edition network protocol.
+    VarInt,
+}
+
+/// The byte order and flavor of NBT data, as taken by the functions in this
+/// module. An `Endianness` converts to the `Format` with that byte order and
+/// fixed-width integers, so it can be passed wherever a `Format` is expected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Format {
+    pub endian: Endianness,
+    pub flavor: Flavor,
+}
+
+impl Format {
+    /// The network format of Bedrock Edition: little-endian, with VarInt
+    /// integers and lengths.
+    pub const NETWORK: Format = Format {
+        endian: Endianness::LittleEndian,
+        flavor: Flavor::VarInt,
+    };
+}
+
+impl From<Endianness> for Format {
+    fn from(endian: Endianness) -> Format {
+        Format {
+            endian,
+            flavor: Flavor::Fixed,
+        }
+    }
+}
+
+/// Calls a `byteorder` read/write method with the byte order of a `Format`.
+macro_rules! with_endian {
+    ($format:expr, $io:ident.$method:ident($($arg:expr),*)) => {
+        match $format.endian {
+            Endianness::BigEndian => $io.$method::<BigEndian>($($arg),*),
+            Endianness::LittleEndian => $io.$method::<LittleEndian>($($arg),*),
+        }
+    };
+}
+
+/// Reads an unsigned VarInt of at most `max` bytes, seven bits per byte
+/// with the least significant group first, refusing longer encodings rather
+/// than reading indefinitely.
+fn read_var_u64<R>(src: &mut R, max: usize) -> Result<u64>
+where
+    R: ?Sized + io::Read,
+{
+    let mut value = 0;
+    for i in 0..max {
+        let byte = src.read_u8()?;
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::VarIntTooLong(max))
+}
+
+/// Writes an unsigned VarInt, the inverse of `read_var_u64`.
+fn write_var_u64<W>(dst: &mut W, mut value: u64) -> Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    while value >= 0x80 {
+        dst.write_u8(value as u8 | 0x80)?;
+        value >>= 7;
+    }
+    dst.write_u8(value as u8).map_err(From::from)
+}
+
 /// A convenience function for closing NBT format objects.
 ///
 /// This function writes a single `0x00` byte to the `io::Write` destination,
 /// which in the NBT format indicates that an open Compound is now closed.
 pub fn close_nbt<W>(dst: &mut W) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
 {
     dst.write_u8(0x00).map_err(From::from)
 }
@@ -21,57 +127,76 @@ where
 #[inline]
 pub fn write_bare_byte<W>(dst: &mut W, value: i8) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
 {
     dst.write_i8(value).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_short<W>(dst: &mut W, value: i16) -> Result<()>
+pub fn write_bare_short<W, E>(dst: &mut W, value: i16, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i16::<BigEndian>(value).map_err(From::from)
+    let format = format.into();
+    with_endian!(format, dst.write_i16(value)).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_int<W>(dst: &mut W, value: i32) -> Result<()>
+pub fn write_bare_int<W, E>(dst: &mut W, value: i32, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i32::<BigEndian>(value).map_err(From::from)
+    let format = format.into();
+    if format.flavor == Flavor::VarInt {
+        // Zigzag encoding keeps small negative values short.
+        return write_var_u64(dst, u64::from(((value << 1) ^ (value >> 31)) as u32));
+    }
+    with_endian!(format, dst.write_i32(value)).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_long<W>(dst: &mut W, value: i64) -> Result<()>
+pub fn write_bare_long<W, E>(dst: &mut W, value: i64, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i64::<BigEndian>(value).map_err(From::from)
+    let format = format.into();
+    if format.flavor == Flavor::VarInt {
+        return write_var_u64(dst, ((value << 1) ^ (value >> 63)) as u64);
+    }
+    with_endian!(format, dst.write_i64(value)).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_float<W>(dst: &mut W, value: f32) -> Result<()>
+pub fn write_bare_float<W, E>(dst: &mut W, value: f32, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_f32::<BigEndian>(value).map_err(From::from)
+    let format = format.into();
+    with_endian!(format, dst.write_f32(value)).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_double<W>(dst: &mut W, value: f64) -> Result<()>
+pub fn write_bare_double<W, E>(dst: &mut W, value: f64, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_f64::<BigEndian>(value).map_err(From::from)
+    let format = format.into();
+    with_endian!(format, dst.write_f64(value)).map_err(From::from)
 }
 
 #[inline]
-pub fn write_bare_byte_array<W>(dst: &mut W, value: &[i8]) -> Result<()>
+pub fn write_bare_byte_array<W, E>(dst: &mut W, value: &[i8], format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i32::<BigEndian>(value.len() as i32)?;
+    let format = format.into();
+    write_bare_int(dst, value.len() as i32, format)?;
     for &v in value {
         dst.write_i8(v)?;
     }
@@ -79,169 +204,507 @@ where
 }
 
 #[inline]
-pub fn write_bare_int_array<W>(dst: &mut W, value: &[i32]) -> Result<()>
+pub fn write_bare_int_array<W, E>(dst: &mut W, value: &[i32], format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i32::<BigEndian>(value.len() as i32)?;
+    let format = format.into();
+    write_bare_int(dst, value.len() as i32, format)?;
     for &v in value {
-        dst.write_i32::<BigEndian>(v)?;
+        write_bare_int(dst, v, format)?;
     }
     Ok(())
 }
 
 #[inline]
-pub fn write_bare_long_array<W>(dst: &mut W, value: &[i64]) -> Result<()>
+pub fn write_bare_long_array<W, E>(dst: &mut W, value: &[i64], format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
-    dst.write_i32::<BigEndian>(value.len() as i32)?;
+    let format = format.into();
+    write_bare_int(dst, value.len() as i32, format)?;
     for &v in value {
-        dst.write_i64::<BigEndian>(v)?;
+        write_bare_long(dst, v, format)?;
     }
     Ok(())
 }
 
 #[inline]
-pub fn write_bare_string<W>(dst: &mut W, value: &str) -> Result<()>
+pub fn write_bare_string<W, E>(dst: &mut W, value: &str, format: E) -> Result<()>
 where
-    W: io::Write,
+    W: ?Sized + io::Write,
+    E: Into<Format>,
 {
+    let format = format.into();
     let encoded = to_java_cesu8(value);
-    dst.write_u16::<BigEndian>(encoded.len() as u16)?;
+    if format.flavor == Flavor::VarInt {
+        write_var_u64(dst, encoded.len() as u64)?;
+    } else {
+        with_endian!(format, dst.write_u16(encoded.len() as u16))?;
+    }
     dst.write_all(&encoded).map_err(From::from)
 }
 
+/// Writes the header (tag and name) of a named entry to an NBT format
+/// destination. This is the inverse of `emit_next_header`.
+pub fn write_header<W, E>(dst: &mut W, tag: u8, name: &str, format: E) -> Result<()>
+where
+    W: ?Sized + io::Write,
+    E: Into<Format>,
+{
+    let format = format.into();
+    dst.write_u8(tag)?;
+    write_bare_string(dst, name, format)
+}
+
 /// Extracts the next header (tag and name) from an NBT format source.
 ///
 /// This function will also return the `TAG_End` byte and an empty name if it
 /// encounters it.
-pub fn emit_next_header<R>(src: &mut R) -> Result<(u8, String)>
+pub fn emit_next_header<R, E>(src: &mut R, format: E) -> Result<(u8, String)>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
+    let format = format.into();
     let tag = src.read_u8()?;
 
     match tag {
         0x00 => Ok((tag, "".to_string())),
         _ => {
-            let name = read_bare_string(src)?;
+            let name = read_bare_string(src, format)?;
             Ok((tag, name))
         }
     }
 }
 
+/// Reads the header of a `TAG_List` payload from an NBT format source,
+/// returning the tag of its elements and the number of elements, without
+/// consuming any of the elements themselves.
+///
+/// Empty lists may have an element tag of `TAG_End`.
+pub fn read_list_header<R, E>(src: &mut R, format: E) -> Result<(u8, i32)>
+where
+    R: ?Sized + io::Read,
+    E: Into<Format>,
+{
+    let format = format.into();
+    let tag = src.read_u8()?;
+    let len = read_bare_int(src, format)?;
+    Ok((tag, len))
+}
+
+/// Reads and discards `len` bytes, failing if the source ends first.
+pub(crate) fn skip_bytes<R>(src: &mut R, len: u64) -> Result<()>
+where
+    R: ?Sized + io::Read,
+{
+    if io::copy(&mut io::Read::take(src, len), &mut io::sink())? < len {
+        return Err(Error::IncompleteNbtValue);
+    }
+    Ok(())
+}
+
+/// Reads past the payload of a tag of type `id` without decoding it,
+/// allowing at most `depth` levels of compounds and lists to be nested.
+/// Calls `visit(id, depth)` for this tag and every tag nested inside it,
+/// before its payload is read.
+pub(crate) fn skip_payload<R, F, E>(
+    src: &mut R,
+    id: u8,
+    format: E,
+    depth: usize,
+    visit: &mut F,
+) -> Result<()>
+where
+    R: ?Sized + io::Read,
+    F: FnMut(u8, usize),
+    E: Into<Format>,
+{
+    let format = format.into();
+    if id == 0x00 || id > 0x0c {
+        return Err(Error::InvalidTypeId(id));
+    }
+    visit(id, depth);
+    match id {
+        0x01 => skip_bytes(src, 1),
+        0x02 => skip_bytes(src, 2),
+        0x03 | 0x05 => skip_bytes(src, 4),
+        0x04 | 0x06 => skip_bytes(src, 8),
+        0x07 | 0x0b | 0x0c => {
+            let len = read_bare_int(src, format)?.max(0) as u64;
+            let size = match id {
+                0x07 => 1,
+                0x0b => 4,
+                _ => 8,
+            };
+            skip_bytes(src, len * size)
+        }
+        0x08 => {
+            let len = read_string_len(src, format)?;
+            skip_bytes(src, len as u64)
+        }
+        _ if depth == 0 => Err(Error::DepthLimitExceeded),
+        0x09 => {
+            let (id, len) = read_list_header(src, format)?;
+            for _ in 0..len {
+                skip_payload(src, id, format, depth - 1, visit)?;
+            }
+            Ok(())
+        }
+        _ => loop {
+            let id = read_bare_byte(src)? as u8;
+            if id == 0x00 {
+                return Ok(());
+            }
+            let len = read_string_len(src, format)?;
+            skip_bytes(src, len as u64)?;
+            skip_payload(src, id, format, depth - 1, visit)?;
+        },
+    }
+}
+
 #[inline]
 pub fn read_bare_byte<R>(src: &mut R) -> Result<i8>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
 {
     src.read_i8().map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_short<R>(src: &mut R) -> Result<i16>
+pub fn read_bare_short<R, E>(src: &mut R, format: E) -> Result<i16>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    src.read_i16::<BigEndian>().map_err(From::from)
+    let format = format.into();
+    with_endian!(format, src.read_i16()).map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_int<R>(src: &mut R) -> Result<i32>
+pub fn read_bare_int<R, E>(src: &mut R, format: E) -> Result<i32>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    src.read_i32::<BigEndian>().map_err(From::from)
+    let format = format.into();
+    if format.flavor == Flavor::VarInt {
+        let n = read_var_u64(src, 5)? as u32;
+        return Ok((n >> 1) as i32 ^ -((n & 1) as i32));
+    }
+    with_endian!(format, src.read_i32()).map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_long<R>(src: &mut R) -> Result<i64>
+pub fn read_bare_long<R, E>(src: &mut R, format: E) -> Result<i64>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    src.read_i64::<BigEndian>().map_err(From::from)
+    let format = format.into();
+    if format.flavor == Flavor::VarInt {
+        let n = read_var_u64(src, 10)?;
+        return Ok((n >> 1) as i64 ^ -((n & 1) as i64));
+    }
+    with_endian!(format, src.read_i64()).map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_float<R>(src: &mut R) -> Result<f32>
+pub fn read_bare_float<R, E>(src: &mut R, format: E) -> Result<f32>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    src.read_f32::<BigEndian>().map_err(From::from)
+    let format = format.into();
+    with_endian!(format, src.read_f32()).map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_double<R>(src: &mut R) -> Result<f64>
+pub fn read_bare_double<R, E>(src: &mut R, format: E) -> Result<f64>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    src.read_f64::<BigEndian>().map_err(From::from)
+    let format = format.into();
+    with_endian!(format, src.read_f64()).map_err(From::from)
 }
 
 #[inline]
-pub fn read_bare_byte_array<R>(src: &mut R) -> Result<Vec<i8>>
+pub fn read_bare_byte_array<R, E>(src: &mut R, format: E) -> Result<Vec<i8>>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    // FIXME: Is there a way to return [u8; len]?
-    let len = src.read_i32::<BigEndian>()? as usize;
-    let mut buf = Vec::with_capacity(len);
-    // FIXME: Test performance vs transmute.
-    for _ in 0..len {
-        buf.push(src.read_i8()?);
+    let format = format.into();
+    let buf = read_bare_bytes(src, format)?;
+    Ok(buf.into_iter().map(|b| b as i8).collect())
+}
+
+/// Reads the payload of a `TAG_Byte_Array` as unsigned bytes, in bulk.
+///
+/// Returns `Error::IncompleteNbtValue` if the source ends before the declared
+/// number of bytes has been read.
+pub fn read_bare_bytes<R, E>(src: &mut R, format: E) -> Result<Vec<u8>>
+where
+    R: ?Sized + io::Read,
+    E: Into<Format>,
+{
+    let format = format.into();
+    let len = read_bare_int(src, format)? as usize;
+    // Don't trust the declared length for the initial allocation.
+    let mut buf = Vec::new();
+    (&mut *src).take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(Error::IncompleteNbtValue);
     }
     Ok(buf)
 }
 
 #[inline]
-pub fn read_bare_int_array<R>(src: &mut R) -> Result<Vec<i32>>
+pub fn read_bare_int_array<R, E>(src: &mut R, format: E) -> Result<Vec<i32>>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
+    let format = format.into();
     // FIXME: Is there a way to return [i32; len]?
-    let len = src.read_i32::<BigEndian>()? as usize;
+    let len = read_bare_int(src, format)? as usize;
     let mut buf = Vec::with_capacity(len);
     // FIXME: Test performance vs transmute.
     for _ in 0..len {
-        buf.push(src.read_i32::<BigEndian>()?);
+        buf.push(read_bare_int(src, format)?);
     }
     Ok(buf)
 }
 
 #[inline]
-pub fn read_bare_long_array<R>(src: &mut R) -> Result<Vec<i64>>
+pub fn read_bare_long_array<R, E>(src: &mut R, format: E) -> Result<Vec<i64>>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    let len = src.read_i32::<BigEndian>()? as usize;
+    let format = format.into();
+    let len = read_bare_int(src, format)? as usize;
     let mut buf = Vec::with_capacity(len);
     for _ in 0..len {
-        buf.push(src.read_i64::<BigEndian>()?);
+        buf.push(read_bare_long(src, format)?);
     }
     Ok(buf)
 }
 
 #[inline]
-pub fn read_bare_string<R>(src: &mut R) -> Result<String>
+pub fn read_bare_string<R, E>(src: &mut R, format: E) -> Result<String>
 where
-    R: io::Read,
+    R: ?Sized + io::Read,
+    E: Into<Format>,
 {
-    let len = src.read_u16::<BigEndian>()? as usize;
+    let len = read_string_len(src, format.into())?;
 
     if len == 0 {
         return Ok("".to_string());
     }
 
-    let mut bytes = vec![0; len];
-    let mut n_read = 0usize;
-    while n_read < bytes.len() {
-        match src.read(&mut bytes[n_read..])? {
-            0 => return Err(Error::IncompleteNbtValue),
-            n => n_read += n,
-        }
+    // Don't trust the declared length for the initial allocation, which a
+    // VarInt length could make as large as 4 GiB.
+    let mut bytes = Vec::new();
+    (&mut *src).take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(Error::IncompleteNbtValue);
     }
 
+    // Valid UTF-8 is accepted as-is before falling back to CESU-8, so strings
+    // written as plain UTF-8 by older versions of this crate still decode.
     let decoded = from_java_cesu8(&bytes)?;
     Ok(decoded.into_owned())
 }
+
+/// Reads the length of a string, which is unsigned.
+fn read_string_len<R>(src: &mut R, format: Format) -> Result<usize>
+where
+    R: ?Sized + io::Read,
+{
+    if format.flavor == Flavor::VarInt {
+        return Ok(read_var_u64(src, 5)? as u32 as usize);
+    }
+    Ok(with_endian!(format, src.read_u16())? as usize)
+}
+
+/// An NBT format destination which writes bare values with a fixed byte
+/// order, as an alternative to passing an `Endianness` to each of the free
+/// functions in this module.
+///
+/// ```rust
+/// use nbt::raw::RawWriter;
+/// use nbt::Endianness;
+///
+/// let mut dst = RawWriter::new(Vec::new(), Endianness::LittleEndian);
+/// dst.write_int(1).unwrap();
+/// assert_eq!(dst.into_inner(), vec![0x01, 0x00, 0x00, 0x00]);
+/// ```
+#[derive(Debug)]
+pub struct RawWriter<W> {
+    dst: W,
+    endian: Endianness,
+}
+
+impl<W> RawWriter<W>
+where
+    W: io::Write,
+{
+    /// Creates a writer for the given destination and byte order.
+    pub fn new(dst: W, endian: Endianness) -> Self {
+        RawWriter { dst, endian }
+    }
+
+    /// The byte order this writer uses.
+    pub fn endian(&self) -> Endianness {
+        self.endian
+    }
+
+    /// Gets a mutable reference to the underlying destination.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.dst
+    }
+
+    /// Unwraps this writer, returning the underlying destination.
+    pub fn into_inner(self) -> W {
+        self.dst
+    }
+
+    pub fn write_byte(&mut self, value: i8) -> Result<()> {
+        write_bare_byte(&mut self.dst, value)
+    }
+
+    pub fn write_short(&mut self, value: i16) -> Result<()> {
+        write_bare_short(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_int(&mut self, value: i32) -> Result<()> {
+        write_bare_int(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_long(&mut self, value: i64) -> Result<()> {
+        write_bare_long(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_float(&mut self, value: f32) -> Result<()> {
+        write_bare_float(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_double(&mut self, value: f64) -> Result<()> {
+        write_bare_double(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_byte_array(&mut self, value: &[i8]) -> Result<()> {
+        write_bare_byte_array(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_int_array(&mut self, value: &[i32]) -> Result<()> {
+        write_bare_int_array(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_long_array(&mut self, value: &[i64]) -> Result<()> {
+        write_bare_long_array(&mut self.dst, value, self.endian)
+    }
+
+    pub fn write_string(&mut self, value: &str) -> Result<()> {
+        write_bare_string(&mut self.dst, value, self.endian)
+    }
+
+    /// Writes the header (tag and name) of a named entry.
+    pub fn write_header(&mut self, tag: u8, name: &str) -> Result<()> {
+        write_header(&mut self.dst, tag, name, self.endian)
+    }
+
+    /// Writes the `TAG_End` byte which closes a compound.
+    pub fn close_nbt(&mut self) -> Result<()> {
+        close_nbt(&mut self.dst)
+    }
+}
+
+/// An NBT format source which reads bare values with a fixed byte order, as
+/// an alternative to passing an `Endianness` to each of the free functions in
+/// this module.
+#[derive(Debug)]
+pub struct RawReader<R> {
+    src: R,
+    endian: Endianness,
+}
+
+impl<R> RawReader<R>
+where
+    R: io::Read,
+{
+    /// Creates a reader for the given source and byte order.
+    pub fn new(src: R, endian: Endianness) -> Self {
+        RawReader { src, endian }
+    }
+
+    /// The byte order this reader uses.
+    pub fn endian(&self) -> Endianness {
+        self.endian
+    }
+
+    /// Gets a mutable reference to the underlying source.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.src
+    }
+
+    /// Unwraps this reader, returning the underlying source.
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+
+    pub fn read_byte(&mut self) -> Result<i8> {
+        read_bare_byte(&mut self.src)
+    }
+
+    pub fn read_short(&mut self) -> Result<i16> {
+        read_bare_short(&mut self.src, self.endian)
+    }
+
+    pub fn read_int(&mut self) -> Result<i32> {
+        read_bare_int(&mut self.src, self.endian)
+    }
+
+    pub fn read_long(&mut self) -> Result<i64> {
+        read_bare_long(&mut self.src, self.endian)
+    }
+
+    pub fn read_float(&mut self) -> Result<f32> {
+        read_bare_float(&mut self.src, self.endian)
+    }
+
+    pub fn read_double(&mut self) -> Result<f64> {
+        read_bare_double(&mut self.src, self.endian)
+    }
+
+    pub fn read_byte_array(&mut self) -> Result<Vec<i8>> {
+        read_bare_byte_array(&mut self.src, self.endian)
+    }
+
+    pub fn read_int_array(&mut self) -> Result<Vec<i32>> {
+        read_bare_int_array(&mut self.src, self.endian)
+    }
+
+    pub fn read_long_array(&mut self) -> Result<Vec<i64>> {
+        read_bare_long_array(&mut self.src, self.endian)
+    }
+
+    pub fn read_string(&mut self) -> Result<String> {
+        read_bare_string(&mut self.src, self.endian)
+    }
+
+    /// Reads the next header (tag and name), as `emit_next_header` does.
+    pub fn read_header(&mut self) -> Result<(u8, String)> {
+        emit_next_header(&mut self.src, self.endian)
+    }
+
+    /// Reads the header of a `TAG_List` payload, as `read_list_header` does.
+    pub fn read_list_header(&mut self) -> Result<(u8, i32)> {
+        read_list_header(&mut self.src, self.endian)
+    }
+}
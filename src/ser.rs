@@ -1,5 +1,6 @@
 //! Serialize a Rust data structure into Named Binary Tag data.
 
+use std::fmt;
 use std::io;
 
 use flate2::write::{GzEncoder, ZlibEncoder};
@@ -7,7 +8,7 @@ use flate2::Compression;
 use serde;
 use serde::ser;
 
-use raw;
+use raw::{self, Endianness};
 
 use error::{Error, Result};
 use serde::ser::Error as SerError;
@@ -20,10 +21,46 @@ where
     W: ?Sized + io::Write,
     T: ?Sized + ser::Serialize,
 {
-    let mut encoder = Encoder::new(dst, header);
+    to_writer_endian(dst, value, header, Endianness::BigEndian)
+}
+
+/// Encode `value` in Named Binary Tag format to the given `io::Write`
+/// destination, with an optional header, writing multi-byte values with the
+/// given byte order.
+pub fn to_writer_endian<W, T>(
+    dst: &mut W,
+    value: &T,
+    header: Option<&str>,
+    endian: Endianness,
+) -> Result<()>
+where
+    W: ?Sized + io::Write,
+    T: ?Sized + ser::Serialize,
+{
+    let mut encoder = Encoder::with_endian(dst, header, endian);
     value.serialize(&mut encoder)
 }
 
+/// Encode just the payload of `value` in Named Binary Tag format to the given
+/// `io::Write` destination, without a tag or name, for protocols which embed
+/// bare NBT values. This is the counterpart of `Value::to_writer` for types
+/// implementing `Serialize`.
+///
+/// Only scalars, arrays, and lists can be written this way; compounds (e.g.
+/// maps and structs) return `Error::UnrepresentableType`, since they must
+/// be written with `to_writer` as the named root of a document.
+pub fn to_bare_writer<W, T>(dst: &mut W, value: &T) -> Result<()>
+where
+    W: ?Sized + io::Write,
+    T: ?Sized + ser::Serialize,
+{
+    if tag_of(value)? == Some(0x0a) {
+        return Err(Error::UnrepresentableType("bare compound"));
+    }
+    let mut encoder = Encoder::new(dst, None);
+    value.serialize(&mut InnerEncoder::from_outer(&mut encoder))
+}
+
 /// Encode `value` in Named Binary Tag format to the given `io::Write`
 /// destination, with an optional header.
 pub fn to_gzip_writer<'a, W, T>(dst: &mut W, value: &T, header: Option<&'a str>) -> Result<()>
@@ -32,7 +69,9 @@ where
     T: ?Sized + ser::Serialize,
 {
     let mut encoder = Encoder::new(GzEncoder::new(dst, Compression::default()), header);
-    value.serialize(&mut encoder)
+    value.serialize(&mut encoder)?;
+    encoder.writer.finish()?;
+    Ok(())
 }
 
 /// Encode `value` in Named Binary Tag format to the given `io::Write`
@@ -43,7 +82,9 @@ where
     T: ?Sized + ser::Serialize,
 {
     let mut encoder = Encoder::new(ZlibEncoder::new(dst, Compression::default()), header);
-    value.serialize(&mut encoder)
+    value.serialize(&mut encoder)?;
+    encoder.writer.finish()?;
+    Ok(())
 }
 
 /// Encode objects to Named Binary Tag format.
@@ -55,6 +96,8 @@ where
 pub struct Encoder<'a, W> {
     writer: W,
     header: Option<&'a str>,
+    endian: Endianness,
+    stringify_keys: bool,
 }
 
 impl<'a, W> Encoder<'a, W>
@@ -63,17 +106,34 @@ where
 {
     /// Create an encoder with optional `header` from a given Writer.
     pub fn new(writer: W, header: Option<&'a str>) -> Self {
-        Encoder { writer, header }
+        Encoder::with_endian(writer, header, Endianness::BigEndian)
+    }
+
+    /// Create an encoder with optional `header` from a given Writer, which
+    /// writes multi-byte values with the given byte order.
+    pub fn with_endian(writer: W, header: Option<&'a str>, endian: Endianness) -> Self {
+        Encoder {
+            writer,
+            header,
+            endian,
+            stringify_keys: false,
+        }
+    }
+
+    /// Sets whether map keys which are not strings, such as the integer keys
+    /// of a `HashMap<i32, T>`, are written in their string form, since the
+    /// keys of a `Tag_Compound` must be strings. This is off by default, and
+    /// such keys are rejected with `Error::NonStringMapKey`.
+    pub fn stringify_keys(mut self, stringify: bool) -> Self {
+        self.stringify_keys = stringify;
+        self
     }
 
     /// Write the NBT tag and an optional header to the underlying writer.
     #[inline]
     fn write_header(&mut self, tag: i8, header: Option<&str>) -> Result<()> {
-        raw::write_bare_byte(&mut self.writer, tag)?;
-        match header {
-            None => raw::write_bare_short(&mut self.writer, 0).map_err(From::from),
-            Some(h) => raw::write_bare_string(&mut self.writer, h).map_err(From::from),
-        }
+        let name = header.unwrap_or("");
+        raw::write_header(&mut self.writer, tag as u8, name, self.endian)
     }
 }
 
@@ -96,6 +156,12 @@ pub struct Compound<'a, 'b: 'a, W: 'a> {
     outer: &'a mut Encoder<'b, W>,
     length: i32,
     sigil: bool,
+    /// Holds the elements of a list whose length was not known upfront, led
+    /// by the tag of the first element, until the list is complete.
+    buffer: Option<Vec<u8>>,
+    /// The tag of the first element of a list, which every other element
+    /// must share.
+    tag: Option<u8>,
 }
 
 impl<'a, 'b, W> Compound<'a, 'b, W>
@@ -107,6 +173,8 @@ where
             outer,
             length: 0,
             sigil: false,
+            buffer: None,
+            tag: None,
         }
     }
 
@@ -118,14 +186,44 @@ where
                 raw::write_bare_byte(&mut outer.writer, 0x00)?;
             }
             // Write list/array length
-            raw::write_bare_int(&mut outer.writer, length)?;
+            raw::write_bare_int(&mut outer.writer, length, outer.endian)?;
         }
         Ok(Compound {
             outer,
             length,
             sigil: false,
+            buffer: None,
+            tag: None,
         })
     }
+
+    /// Creates a list whose elements are buffered and counted, so that its
+    /// header can be written once the length is known.
+    fn for_unsized_seq(outer: &'a mut Encoder<'b, W>) -> Self {
+        Compound {
+            outer,
+            length: 0,
+            sigil: false,
+            buffer: Some(Vec::new()),
+            tag: None,
+        }
+    }
+}
+
+/// Returns the tag with which `value` will be written, or `None` if it writes
+/// no tag at all (e.g. `None`).
+fn tag_of<T>(value: &T) -> Result<Option<u8>>
+where
+    T: ?Sized + serde::Serialize,
+{
+    let mut tag = [0u8];
+    let mut encoder = Encoder::new(&mut tag[..], None);
+    value.serialize(&mut TagEncoder::from_outer(
+        &mut encoder,
+        Option::<String>::None,
+    ))?;
+    let written = encoder.writer.is_empty();
+    Ok(if written { Some(tag[0]) } else { None })
 }
 
 impl<'a, 'b, W> ser::SerializeSeq for Compound<'a, 'b, W>
@@ -139,18 +237,51 @@ where
     where
         T: serde::Serialize,
     {
-        if !self.sigil {
-            value.serialize(&mut TagEncoder::from_outer(
-                self.outer,
-                Option::<String>::None,
-            ))?;
-            raw::write_bare_int(&mut self.outer.writer, self.length)?;
+        // NBT lists cannot hold a mixture of types, so every element must
+        // have the same tag as the first.
+        let tag = tag_of(value)?;
+        let first = match self.buffer {
+            Some(_) => self.length == 0,
+            None => !self.sigil,
+        };
+        if first {
+            self.tag = tag;
+        } else if tag != self.tag {
+            return Err(Error::HeterogeneousList);
+        }
+
+        if let Some(ref mut buffer) = self.buffer {
+            if first {
+                buffer.extend(tag);
+            }
+            let mut encoder = Encoder::with_endian(buffer, None, self.outer.endian)
+                .stringify_keys(self.outer.stringify_keys);
+            value.serialize(&mut InnerEncoder::from_outer(&mut encoder))?;
+            self.length += 1;
+            return Ok(());
+        }
+        if first {
+            if let Some(tag) = tag {
+                raw::write_bare_byte(&mut self.outer.writer, tag as i8)?;
+            }
+            raw::write_bare_int(&mut self.outer.writer, self.length, self.outer.endian)?;
             self.sigil = true;
         }
         value.serialize(&mut InnerEncoder::from_outer(self.outer))
     }
 
     fn end(self) -> Result<()> {
+        if let Some(buffer) = self.buffer {
+            // An empty list has no elements to take the tag from, so use
+            // TAG_End as for other empty lists.
+            let (tag, elements) = match buffer.split_first() {
+                Some((&tag, elements)) => (tag, elements),
+                None => (0x00, &[][..]),
+            };
+            raw::write_bare_byte(&mut self.outer.writer, tag as i8)?;
+            raw::write_bare_int(&mut self.outer.writer, self.length, self.outer.endian)?;
+            self.outer.writer.write_all(elements)?;
+        }
         Ok(())
     }
 }
@@ -299,8 +430,7 @@ where
     type SerializeStructVariant = ser::Impossible<(), Error>;
 
     unrepresentable!(
-        u8 u16 u32 u64 char unit newtype_variant tuple
-            tuple_variant struct_variant
+        u8 u16 u32 u64 char unit tuple tuple_variant struct_variant
     );
 
     #[inline]
@@ -315,32 +445,50 @@ where
 
     #[inline]
     fn serialize_i16(self, value: i16) -> Result<()> {
-        raw::write_bare_short(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_short(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<()> {
-        raw::write_bare_int(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_int(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> Result<()> {
-        raw::write_bare_long(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_long(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
+    }
+
+    /// Serialize 128-bit integers, such as UUIDs, as a `Tag_IntArray` of four
+    /// integers, most significant first.
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.serialize_u128(value as u128)
+    }
+
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        let words = [
+            (value >> 96) as i32,
+            (value >> 64) as i32,
+            (value >> 32) as i32,
+            value as i32,
+        ];
+        raw::write_bare_int_array(&mut self.outer.writer, &words, self.outer.endian)
     }
 
     #[inline]
     fn serialize_f32(self, value: f32) -> Result<()> {
-        raw::write_bare_float(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_float(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
     }
 
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<()> {
-        raw::write_bare_double(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_double(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
     }
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        raw::write_bare_string(&mut self.outer.writer, value).map_err(From::from)
+        raw::write_bare_string(&mut self.outer.writer, value, self.outer.endian).map_err(From::from)
     }
 
     #[inline]
@@ -353,9 +501,28 @@ where
         self.serialize_str(variant)
     }
 
+    /// Serialize newtype variants as a `Tag_Compound` with a single entry,
+    /// keyed by the variant name.
     #[inline]
-    fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
-        Err(Error::UnrepresentableType("u8"))
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        value.serialize(&mut TagEncoder::from_outer(self.outer, Some(variant)))?;
+        value.serialize(&mut InnerEncoder::from_outer(self.outer))?;
+        raw::close_nbt(&mut self.outer.writer)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        let value: Vec<i8> = value.iter().map(|&b| b as i8).collect();
+        raw::write_bare_byte_array(&mut self.outer.writer, &value, self.outer.endian)
     }
 
     #[inline]
@@ -389,7 +556,7 @@ where
         if let Some(l) = len {
             Compound::for_seq(self.outer, l as i32, false)
         } else {
-            Err(Error::UnrepresentableType("unsized list"))
+            Ok(Compound::for_unsized_seq(self.outer))
         }
     }
 
@@ -429,6 +596,19 @@ where
     pub fn from_outer(outer: &'a mut Encoder<'b, W>) -> Self {
         MapKeyEncoder { outer }
     }
+
+    /// Writes a key that is not a string in its `Display` form, if the
+    /// encoder allows it.
+    fn serialize_display<T: fmt::Display>(&mut self, value: T) -> Result<()> {
+        if !self.outer.stringify_keys {
+            return Err(Error::NonStringMapKey);
+        }
+        raw::write_bare_string(
+            &mut self.outer.writer,
+            &value.to_string(),
+            self.outer.endian,
+        )
+    }
 }
 
 impl<'a, 'b: 'a, W: 'a> serde::Serializer for &'a mut MapKeyEncoder<'a, 'b, W>
@@ -446,11 +626,27 @@ where
     type SerializeStructVariant = ser::Impossible<(), Error>;
 
     return_expr_for_serialized_types!(
-        Err(Error::NonStringMapKey); bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64
-            char bytes unit unit_variant newtype_variant unit_struct seq tuple
-            tuple_struct tuple_variant struct_variant newtype_struct map struct
+        Err(Error::NonStringMapKey); bytes unit newtype_variant unit_struct seq
+            tuple tuple_struct tuple_variant struct_variant newtype_struct map
+            struct
     );
 
+    stringify_map_keys!(
+        serialize_bool(bool) serialize_i8(i8) serialize_i16(i16)
+        serialize_i32(i32) serialize_i64(i64) serialize_u8(u8)
+        serialize_u16(u16) serialize_u32(u32) serialize_u64(u64)
+        serialize_f32(f32) serialize_f64(f64) serialize_char(char)
+    );
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_display(variant)
+    }
+
     fn serialize_none(self) -> Result<()> {
         Ok(())
     }
@@ -463,7 +659,7 @@ where
     }
 
     fn serialize_str(self, value: &str) -> Result<()> {
-        raw::write_bare_string(&mut self.outer.writer, value)
+        raw::write_bare_string(&mut self.outer.writer, value, self.outer.endian)
     }
 }
 
@@ -506,8 +702,7 @@ where
     type SerializeStructVariant = ser::Impossible<(), Error>;
 
     unrepresentable!(
-        u8 u16 u32 u64 char unit newtype_variant tuple
-            tuple_variant struct_variant
+        u8 u16 u32 u64 char unit tuple tuple_variant struct_variant
     );
 
     #[inline]
@@ -535,6 +730,16 @@ where
         self.write_header(0x04)
     }
 
+    #[inline]
+    fn serialize_i128(self, _value: i128) -> Result<()> {
+        self.write_header(0x0b)
+    }
+
+    #[inline]
+    fn serialize_u128(self, _value: u128) -> Result<()> {
+        self.write_header(0x0b)
+    }
+
     #[inline]
     fn serialize_f32(self, _value: f32) -> Result<()> {
         self.write_header(0x05)
@@ -560,9 +765,23 @@ where
         self.serialize_str(variant)
     }
 
+    #[inline]
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ser::Serialize,
+    {
+        self.write_header(0x0a)
+    }
+
     #[inline]
     fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
-        Err(Error::UnrepresentableType("u8"))
+        self.write_header(0x07)
     }
 
     #[inline]
@@ -592,13 +811,9 @@ where
     }
 
     #[inline]
-    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        if len.is_some() {
-            self.write_header(0x09)?;
-            Ok(NoOp)
-        } else {
-            Err(Error::UnrepresentableType("unsized list"))
-        }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.write_header(0x09)?;
+        Ok(NoOp)
     }
 
     #[inline]
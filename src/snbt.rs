@@ -0,0 +1,346 @@
+//! Parsing and writing of stringified NBT (SNBT), the text format used by
+//! Minecraft commands, e.g. `{Health:20b, Pos:[0.0d,64.0d,0.0d]}`.
+
+use crate::Map;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use error::{Error, Result};
+use options::DEFAULT_MAX_DEPTH;
+use value::Value;
+
+/// Parses a single SNBT value, which may be surrounded by whitespace.
+pub(crate) fn parse(src: &str) -> Result<Value> {
+    let mut parser = Parser {
+        src,
+        chars: src.char_indices().peekable(),
+    };
+    let value = parser.value(DEFAULT_MAX_DEPTH)?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(&(pos, c)) => Err(parser.error(pos, &format!("unexpected '{}'", c))),
+    }
+}
+
+/// Returns whether `c` may appear in an unquoted string or number.
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_-.+".contains(c)
+}
+
+/// Appends the SNBT form of `value` to `out`.
+pub(crate) fn write_value(out: &mut String, value: &Value) {
+    // Writing to a `String` cannot fail.
+    match *value {
+        Value::Byte(v) => write!(out, "{}b", v).unwrap(),
+        Value::Short(v) => write!(out, "{}s", v).unwrap(),
+        Value::Int(v) => write!(out, "{}", v).unwrap(),
+        Value::Long(v) => write!(out, "{}l", v).unwrap(),
+        // SNBT has no literal for infinity, so write a number too large for
+        // the type, which reads back as infinity.
+        Value::Float(v) if v.is_infinite() => {
+            out.push_str(if v < 0.0 { "-1e999f" } else { "1e999f" })
+        }
+        Value::Float(v) => write!(out, "{:?}f", v).unwrap(),
+        Value::Double(v) if v.is_infinite() => {
+            out.push_str(if v < 0.0 { "-1e999d" } else { "1e999d" })
+        }
+        Value::Double(v) => write!(out, "{:?}d", v).unwrap(),
+        Value::ByteArray(ref vals) => write_array(out, 'B', vals.iter().map(|v| format!("{}b", v))),
+        Value::IntArray(ref vals) => write_array(out, 'I', vals.iter().map(|v| v.to_string())),
+        Value::LongArray(ref vals) => write_array(out, 'L', vals.iter().map(|v| format!("{}l", v))),
+        // Strings which would read back as numbers or booleans are quoted.
+        Value::String(ref v) => write_string(out, v, scalar(v).is_some()),
+        Value::List(ref vals) => {
+            out.push('[');
+            for (i, val) in vals.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, val);
+            }
+            out.push(']');
+        }
+        Value::Compound(ref map) => write_compound(out, map),
+    }
+}
+
+/// Appends the SNBT form of a compound with the entries of `map` to `out`.
+pub(crate) fn write_compound(out: &mut String, map: &Map<String, Value>) {
+    out.push('{');
+    for (i, (key, val)) in map.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(out, key, false);
+        out.push(':');
+        write_value(out, val);
+    }
+    out.push('}');
+}
+
+fn write_array<I>(out: &mut String, kind: char, vals: I)
+where
+    I: Iterator<Item = String>,
+{
+    write!(out, "[{};", kind).unwrap();
+    for (i, val) in vals.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&val);
+    }
+    out.push(']');
+}
+
+/// Appends `s` to `out`, in double quotes if it is empty, contains any
+/// character not allowed in an unquoted string, or `quote` is set.
+fn write_string(out: &mut String, s: &str, quote: bool) {
+    if !quote && !s.is_empty() && s.chars().all(is_unquoted_char) {
+        out.push_str(s);
+        return;
+    }
+    out.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, pos: usize, msg: &str) -> Error {
+        Error::SnbtParse(format!("{} at position {}", msg, pos))
+    }
+
+    /// The position of the next character, or the end of the input.
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |&(pos, _)| pos)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    /// Skips whitespace, then consumes `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek().map(|&(_, next)| next) == Some(c) {
+            self.chars.next();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        let pos = self.pos();
+        Err(self.error(pos, &format!("expected '{}'", c)))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('{') | Some('[') if depth == 0 => Err(Error::DepthLimitExceeded),
+            Some('{') => self.compound(depth - 1),
+            Some('[') => self.list(depth - 1),
+            Some('"') | Some('\'') => Ok(Value::String(self.quoted()?)),
+            _ => {
+                let pos = self.pos();
+                let token = self.unquoted();
+                if token.is_empty() {
+                    return Err(self.error(pos, "expected a value"));
+                }
+                Ok(scalar(token).unwrap_or_else(|| Value::String(token.to_string())))
+            }
+        }
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<Value> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        while !self.eat('}') {
+            self.skip_whitespace();
+            let pos = self.pos();
+            // Only a quoted key may be empty.
+            let key = match self.chars.peek().map(|&(_, c)| c) {
+                Some('"') | Some('\'') => self.quoted()?,
+                _ => match self.unquoted() {
+                    "" => return Err(self.error(pos, "expected a key")),
+                    key => key.to_string(),
+                },
+            };
+            self.expect(':')?;
+            let value = self.value(depth)?;
+            map.insert(key, value);
+            if !self.eat(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+        Ok(Value::Compound(map))
+    }
+
+    fn list(&mut self, depth: usize) -> Result<Value> {
+        self.expect('[')?;
+        // Typed arrays are introduced by their element type and a semicolon,
+        // which can't begin an ordinary list element.
+        let mut lookahead = self.chars.clone();
+        let array = match (lookahead.next(), lookahead.next()) {
+            (Some((_, kind @ 'B')), Some((_, ';')))
+            | (Some((_, kind @ 'I')), Some((_, ';')))
+            | (Some((_, kind @ 'L')), Some((_, ';'))) => Some(kind),
+            _ => None,
+        };
+        if array.is_some() {
+            self.chars.next();
+            self.chars.next();
+        }
+
+        let mut vals: Vec<(usize, Value)> = Vec::new();
+        while !self.eat(']') {
+            self.skip_whitespace();
+            let pos = self.pos();
+            let value = self.value(depth)?;
+            if let (None, Some((_, first))) = (array, vals.first()) {
+                if first.id() != value.id() {
+                    let msg = format!(
+                        "list of {} contains a {}",
+                        first.tag_name(),
+                        value.tag_name()
+                    );
+                    return Err(self.error(pos, &msg));
+                }
+            }
+            vals.push((pos, value));
+            if !self.eat(',') {
+                self.expect(']')?;
+                break;
+            }
+        }
+
+        match array {
+            None => Ok(Value::List(vals.into_iter().map(|(_, v)| v).collect())),
+            Some(kind) => {
+                let longs = vals
+                    .into_iter()
+                    .map(|(pos, value)| match value {
+                        Value::Byte(v) => Ok((pos, i64::from(v))),
+                        Value::Short(v) => Ok((pos, i64::from(v))),
+                        Value::Int(v) => Ok((pos, i64::from(v))),
+                        Value::Long(v) => Ok((pos, v)),
+                        other => {
+                            let msg = format!("array contains a {}", other.tag_name());
+                            Err(self.error(pos, &msg))
+                        }
+                    })
+                    .collect::<Result<Vec<(usize, i64)>>>()?;
+                let msg = format!("value out of range for [{};]", kind);
+                let overflow = |pos| self.error(pos, &msg);
+                match kind {
+                    'B' => longs
+                        .into_iter()
+                        .map(|(pos, v)| i8::try_from(v).map_err(|_| overflow(pos)))
+                        .collect::<Result<_>>()
+                        .map(Value::ByteArray),
+                    'I' => longs
+                        .into_iter()
+                        .map(|(pos, v)| i32::try_from(v).map_err(|_| overflow(pos)))
+                        .collect::<Result<_>>()
+                        .map(Value::IntArray),
+                    _ => Ok(Value::LongArray(
+                        longs.into_iter().map(|(_, v)| v).collect(),
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Reads a string in double or single quotes, with backslash escapes.
+    fn quoted(&mut self) -> Result<String> {
+        let (start, quote) = self.chars.next().unwrap();
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error(start, "unterminated string")),
+                Some((_, c)) if c == quote => return Ok(out),
+                Some((pos, '\\')) => match self.chars.next() {
+                    Some((_, c @ '\\')) | Some((_, c @ '"')) | Some((_, c @ '\'')) => out.push(c),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'r')) => out.push('\r'),
+                    _ => return Err(self.error(pos, "invalid escape sequence")),
+                },
+                Some((_, c)) => out.push(c),
+            }
+        }
+    }
+
+    /// Reads a run of the characters allowed in unquoted strings and numbers.
+    fn unquoted(&mut self) -> &'a str {
+        let start = self.pos();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !is_unquoted_char(c) {
+                break;
+            }
+            self.chars.next();
+        }
+        let end = self.pos();
+        &self.src[start..end]
+    }
+}
+
+/// Interprets an unquoted token as a number or boolean, if it is one.
+/// Tokens which look numeric but are out of range for their type are left
+/// as strings, as Minecraft does.
+fn scalar(token: &str) -> Option<Value> {
+    match token {
+        "true" => return Some(Value::Byte(1)),
+        "false" => return Some(Value::Byte(0)),
+        _ => (),
+    }
+    let numeric = match token.chars().next() {
+        Some(c) => c.is_ascii_digit() || "-+.".contains(c),
+        None => false,
+    };
+    if !numeric {
+        return None;
+    }
+    // The token may come from a string being written, which can contain
+    // characters of any width.
+    let (last, _) = token.char_indices().next_back()?;
+    let (body, suffix) = token.split_at(last);
+    // Rust's float parser also accepts words such as `inf` and `NaN`, which
+    // are not numbers in SNBT.
+    if body
+        .chars()
+        .any(|c| c.is_alphabetic() && c != 'e' && c != 'E')
+    {
+        return None;
+    }
+    match suffix {
+        "b" | "B" => body.parse().ok().map(Value::Byte),
+        "s" | "S" => body.parse().ok().map(Value::Short),
+        "l" | "L" => body.parse().ok().map(Value::Long),
+        "f" | "F" => body.parse().ok().map(Value::Float),
+        "d" | "D" => body.parse().ok().map(Value::Double),
+        _ if token.contains(['.', 'e', 'E']) => token.parse().ok().map(Value::Double),
+        _ => token.parse().ok().map(Value::Int),
+    }
+}
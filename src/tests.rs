@@ -4,9 +4,14 @@ use Map;
 
 //use test::Bencher;
 
-use blob::Blob;
-use error::Error;
-use value::Value;
+use annotate::annotate;
+use bits::LongArrayBits;
+use blob::{Blob, BlobStats};
+use compression::{detect_compression, recompress, Compression};
+use error::{Error, RootNames};
+use options::ReadOptions;
+use raw::{self, Endianness, StringEncoding};
+use value::{tag_name_for_id, Value};
 
 #[test]
 fn nbt_nonempty() {
@@ -83,6 +88,137 @@ fn nbt_empty_nbtfile() {
     assert_eq!(&file, &nbt);
 }
 
+#[test]
+fn nbt_with() {
+    let mut expected = Blob::new();
+    expected.insert("health", 100i8).unwrap();
+    assert_eq!(Blob::with("health", 100i8), Ok(expected));
+
+    let badlist = Value::List(vec![Value::Byte(1), Value::Short(1)]);
+    assert_eq!(Blob::with("list", badlist), Err(Error::HeterogeneousList));
+}
+
+#[test]
+fn nbt_with_capacity() {
+    let mut nbt = Blob::with_capacity(16);
+    nbt.insert("health", 100i8).unwrap();
+    nbt.reserve(256);
+    for i in 0..256 {
+        nbt.insert(format!("key{}", i), Value::Int(i)).unwrap();
+    }
+
+    let mut expected = Blob::new();
+    expected.insert("health", 100i8).unwrap();
+    for i in 0..256 {
+        expected.insert(format!("key{}", i), Value::Int(i)).unwrap();
+    }
+    assert_eq!(nbt, expected);
+    assert_eq!(nbt.len_bytes(), expected.len_bytes());
+
+    let mut dst = Vec::new();
+    Blob::with_capacity(4).to_writer(&mut dst).unwrap();
+    assert_eq!(dst, vec![0x0a, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn nbt_content_hash() {
+    let mut pos = Map::new();
+    pos.insert("x".to_string(), Value::Int(1));
+    pos.insert("z".to_string(), Value::Int(2));
+    let mut a = Blob::new();
+    a.insert("id", "minecraft:pig").unwrap();
+    a.insert("health", 10i8).unwrap();
+    a.insert("pos", Value::Compound(pos)).unwrap();
+
+    let mut pos = Map::new();
+    pos.insert("z".to_string(), Value::Int(2));
+    pos.insert("x".to_string(), Value::Int(1));
+    let mut b = Blob::new();
+    b.insert("pos", Value::Compound(pos)).unwrap();
+    b.insert("health", 10i8).unwrap();
+    b.insert("id", "minecraft:pig").unwrap();
+
+    assert_eq!(a.content_hash(), b.content_hash());
+
+    b.insert("health", 9i8).unwrap();
+    assert_ne!(a.content_hash(), b.content_hash());
+    assert_ne!(Blob::new().content_hash(), Blob::named("a").content_hash());
+}
+
+#[test]
+fn nbt_special_floats() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x05,
+                0x00, 0x03,
+                0x6e, 0x61, 0x6e,
+                0x7f, 0xc0, 0x00, 0x00,
+            0x06,
+                0x00, 0x03,
+                0x69, 0x6e, 0x66,
+                0x7f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x05,
+                0x00, 0x04,
+                0x73, 0x6e, 0x61, 0x6e,
+                0x7f, 0x80, 0x00, 0x01,
+        0x00
+    ];
+
+    let file = Blob::from_reader(&mut io::Cursor::new(&bytes[..])).unwrap();
+    match file["nan"] {
+        Value::Float(v) => assert_eq!(v.to_bits(), f32::NAN.to_bits()),
+        _ => panic!("nan was not TAG_Float"),
+    }
+    match file["inf"] {
+        Value::Double(v) => assert_eq!(v, f64::INFINITY),
+        _ => panic!("inf was not TAG_Double"),
+    }
+    match file["snan"] {
+        Value::Float(v) => assert_eq!(v.to_bits(), 0x7f80_0001),
+        _ => panic!("snan was not TAG_Float"),
+    }
+
+    // Writing the values back must not normalize their bit patterns. Check
+    // each entry separately, since entry order is not preserved.
+    for name in &["nan", "inf", "snan"] {
+        let mut nbt = Blob::new();
+        nbt.insert(*name, file[*name].clone()).unwrap();
+        let mut dst = Vec::new();
+        nbt.to_writer(&mut dst).unwrap();
+        // Strip the root header and end tag to get the entry itself.
+        let entry = &dst[3..dst.len() - 1];
+        assert!(bytes.windows(entry.len()).any(|w| w == entry));
+    }
+}
+
+#[test]
+fn nbt_read_options() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x03,
+                0x05, 0x00,
+                0x63, 0x6f, 0x75, 0x6e, 0x74,
+                0x01, 0x02, 0x00, 0x00,
+        0x00
+    ];
+
+    let mut options = ReadOptions::default();
+    assert_eq!(options.endian, Endianness::BigEndian);
+    options.endian = Endianness::LittleEndian;
+
+    let file = Blob::from_reader_with(&mut io::Cursor::new(&bytes[..]), &options).unwrap();
+    assert_eq!(file["count"], Value::Int(0x0201));
+
+    // With the default options, the name length is misread.
+    assert!(
+        Blob::from_reader_with(&mut io::Cursor::new(&bytes[..]), &ReadOptions::default()).is_err()
+    );
+}
+
 #[test]
 fn nbt_nested_compound() {
     let mut inner = Map::new();
@@ -212,6 +348,42 @@ fn nbt_no_end_tag() {
     );
 }
 
+#[test]
+fn nbt_tolerate_missing_end() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x01,
+                0x61,
+                0x01,
+            0x0a,
+                0x00, 0x01,
+                0x62,
+                0x00,
+    ];
+
+    let options = ReadOptions {
+        tolerate_missing_end: true,
+        ..Default::default()
+    };
+    let file = Blob::from_reader_with(&mut io::Cursor::new(&bytes[..]), &options).unwrap();
+    assert_eq!(file["a"], Value::Byte(1));
+    assert_eq!(file["b"], Value::Compound(Map::new()));
+
+    // Only the end of the root compound may be missing.
+    let truncated = &bytes[..bytes.len() - 1];
+    assert_eq!(
+        Blob::from_reader_with(&mut io::Cursor::new(truncated), &options),
+        Err(Error::IncompleteNbtValue)
+    );
+    assert_eq!(
+        Blob::from_reader(&mut io::Cursor::new(&bytes[..])),
+        Err(Error::IncompleteNbtValue)
+    );
+}
+
 #[test]
 fn nbt_invalid_id() {
     #[rustfmt::skip]
@@ -243,6 +415,33 @@ fn nbt_invalid_list() {
     );
 }
 
+#[test]
+fn nbt_validate() {
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+    assert_eq!(nbt.validate(), Ok(()));
+
+    // A heterogeneous list nested inside a compound is not caught by insert.
+    let mut inner = Map::new();
+    inner.insert(
+        "list".to_string(),
+        Value::List(vec![Value::List(vec![Value::Byte(1), Value::Short(1)])]),
+    );
+    nbt.insert("inner", Value::Compound(inner)).unwrap();
+    assert_eq!(nbt.validate(), Err(Error::HeterogeneousList));
+
+    let long = "a".repeat(65536);
+    assert_eq!(
+        Value::String(long.clone()).validate(),
+        Err(Error::LengthOverflow("TAG_String"))
+    );
+    assert_eq!(Value::String("a".repeat(65535)).validate(), Ok(()));
+    assert_eq!(
+        Blob::named(long).validate(),
+        Err(Error::LengthOverflow("TAG_String"))
+    );
+}
+
 #[test]
 fn nbt_bad_compression() {
     // These aren't in the zlib or gzip format, so they'll fail.
@@ -251,6 +450,102 @@ fn nbt_bad_compression() {
     assert!(Blob::from_zlib_reader(&mut io::Cursor::new(&bytes[..])).is_err());
 }
 
+#[test]
+fn nbt_truncated_gzip() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+    let mut gzip = Vec::new();
+    nbt.to_gzip_writer(&mut gzip).unwrap();
+    // Cut off part of the compressed body and the trailer.
+    gzip.truncate(gzip.len() - 12);
+
+    match Blob::from_gzip_reader(&mut io::Cursor::new(&gzip[..])) {
+        Err(Error::Decompression(_)) => (),
+        other => panic!("expected a decompression error, got {:?}", other),
+    }
+
+    // An intact stream holding a short NBT body is still reported as such.
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&[0x0a, 0x00, 0x00, 0x01]).unwrap();
+    let gzip = encoder.finish().unwrap();
+    assert_eq!(
+        Blob::from_gzip_reader(&mut io::Cursor::new(&gzip[..])),
+        Err(Error::IncompleteNbtValue)
+    );
+
+    // Failures of the source itself are not decompression errors.
+    struct FailingReader;
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "disk error",
+            ))
+        }
+    }
+
+    match Blob::from_gzip_reader(&mut FailingReader) {
+        Err(Error::IoError(ref e)) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+}
+
+#[test]
+fn nbt_detect_compression() {
+    let nbt = Blob::with("health", 100i8).unwrap();
+
+    let mut gzip = Vec::new();
+    nbt.to_gzip_writer(&mut gzip).unwrap();
+    assert_eq!(detect_compression(&gzip), Some(Compression::Gzip));
+
+    let mut zlib = Vec::new();
+    nbt.to_zlib_writer(&mut zlib).unwrap();
+    assert_eq!(detect_compression(&zlib), Some(Compression::Zlib));
+    assert_eq!(detect_compression(&[0x78, 0x9c]), Some(Compression::Zlib));
+    assert_eq!(detect_compression(&[0x78, 0x9d]), None);
+
+    let mut plain = Vec::new();
+    nbt.to_writer(&mut plain).unwrap();
+    assert_eq!(detect_compression(&plain), None);
+    assert_eq!(detect_compression(&[0x1f]), None);
+    assert_eq!(detect_compression(&[]), None);
+}
+
+#[test]
+fn nbt_recompress() {
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+    nbt.insert("health", 100i8).unwrap();
+
+    let mut gzip = Vec::new();
+    nbt.to_gzip_writer(&mut gzip).unwrap();
+
+    // Borrowed sources and destinations work too.
+    let mut zlib = Vec::new();
+    recompress(&gzip[..], &mut zlib, Compression::Gzip, Compression::Zlib).unwrap();
+    assert_eq!(detect_compression(&zlib), Some(Compression::Zlib));
+    let read = Blob::from_zlib_reader(&mut io::Cursor::new(&zlib)).unwrap();
+    assert_eq!(read, nbt);
+
+    let mut back = Vec::new();
+    recompress(&zlib[..], &mut back, Compression::Zlib, Compression::Gzip).unwrap();
+    let read = Blob::from_gzip_reader(&mut io::Cursor::new(&back)).unwrap();
+    assert_eq!(read, nbt);
+
+    // Uncompressed input is rejected rather than copied.
+    let mut plain = Vec::new();
+    nbt.to_writer(&mut plain).unwrap();
+    match recompress(&plain[..], Vec::new(), Compression::Gzip, Compression::Zlib) {
+        Err(Error::Decompression(_)) => (),
+        other => panic!("expected a decompression error, found {:?}", other),
+    }
+}
+
 #[test]
 fn nbt_compression() {
     // Create a non-trivial Blob.
@@ -275,6 +570,110 @@ fn nbt_compression() {
     assert_eq!(&nbt, &gz_file);
 }
 
+#[test]
+fn nbt_multigzip() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+    nbt.insert("health", 100i8).unwrap();
+
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+
+    // Split the document across two separate gzip members.
+    let (first, second) = bytes.split_at(bytes.len() / 2);
+    let mut dst = Vec::new();
+    for part in &[first, second] {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(part).unwrap();
+        dst.extend(encoder.finish().unwrap());
+    }
+
+    let file = Blob::from_multigzip_reader(&mut io::Cursor::new(&dst[..])).unwrap();
+    assert_eq!(&file, &nbt);
+
+    // A plain gzip reader only sees the first member.
+    assert!(Blob::from_gzip_reader(&mut io::Cursor::new(&dst[..])).is_err());
+}
+
+#[test]
+fn nbt_dyn_reader() {
+    let mut nbt = Blob::new();
+    nbt.insert("health", 100i8).unwrap();
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+
+    let mut src = io::Cursor::new(bytes.clone());
+    let reader: &mut dyn io::Read = &mut src;
+    assert_eq!(Blob::from_reader(reader).unwrap(), nbt);
+
+    // Skip the root header and read the payload directly.
+    let mut src = io::Cursor::new(&bytes[3..]);
+    let reader: &mut dyn io::Read = &mut src;
+    let value = Value::from_reader(0x0a, reader).unwrap();
+    assert_eq!(value.tag_name(), "TAG_Compound");
+
+    let mut gzip = Vec::new();
+    nbt.to_gzip_writer(&mut gzip).unwrap();
+    let mut src = io::Cursor::new(gzip);
+    let reader: &mut dyn io::Read = &mut src;
+    assert_eq!(Blob::from_gzip_reader(reader).unwrap(), nbt);
+
+    #[cfg(feature = "serde")]
+    {
+        let mut src = io::Cursor::new(bytes);
+        let reader: &mut dyn io::Read = &mut src;
+        let file: Blob = ::de::from_reader(reader).unwrap();
+        assert_eq!(file, nbt);
+    }
+}
+
+#[test]
+fn nbt_dyn_writer() {
+    let mut nbt = Blob::new();
+    nbt.insert("health", 100i8).unwrap();
+    let mut expected = Vec::new();
+    nbt.to_writer(&mut expected).unwrap();
+
+    let mut bytes = Vec::new();
+    {
+        let writer: &mut dyn io::Write = &mut bytes;
+        nbt.to_writer(writer).unwrap();
+    }
+    assert_eq!(bytes, expected);
+
+    let mut gzip = Vec::new();
+    {
+        let writer: &mut dyn io::Write = &mut gzip;
+        nbt.to_gzip_writer(writer).unwrap();
+    }
+    assert_eq!(
+        Blob::from_gzip_reader(&mut io::Cursor::new(gzip)).unwrap(),
+        nbt
+    );
+
+    let mut zlib = Vec::new();
+    {
+        let writer: &mut dyn io::Write = &mut zlib;
+        nbt.to_zlib_writer(writer).unwrap();
+    }
+    assert_eq!(
+        Blob::from_zlib_reader(&mut io::Cursor::new(zlib)).unwrap(),
+        nbt
+    );
+
+    let value = Value::Int(7);
+    let mut payload = Vec::new();
+    {
+        let writer: &mut dyn io::Write = &mut payload;
+        value.to_writer(writer).unwrap();
+    }
+    assert_eq!(payload, vec![0x00, 0x00, 0x00, 0x07]);
+}
+
 #[test]
 fn nbt_bigtest() {
     let mut bigtest_file = File::open("tests/big1.nbt").unwrap();
@@ -305,6 +704,104 @@ fn nbt_arrays() {
     }
 }
 
+#[test]
+fn nbt_bulk_byte_array() {
+    use byteorder::{BigEndian, ReadBytesExt};
+
+    let bytes = std::fs::read("tests/arrays.nbt").unwrap();
+    let mut src = io::Cursor::new(&bytes[..]);
+    let endian = Endianness::BigEndian;
+    assert_eq!(raw::emit_next_header(&mut src, endian).unwrap().0, 0x0a);
+    let mut found = 0;
+    loop {
+        let (id, _) = raw::emit_next_header(&mut src, endian).unwrap();
+        match id {
+            0x00 => break,
+            0x07 => {
+                let start = src.position();
+                let bulk = raw::read_bare_byte_array(&mut src, endian).unwrap();
+
+                // Compare against reading one byte at a time.
+                src.set_position(start);
+                let len = src.read_i32::<BigEndian>().unwrap();
+                let single: Vec<i8> = (0..len).map(|_| src.read_i8().unwrap()).collect();
+                assert_eq!(bulk, single);
+                found += 1;
+            }
+            _ => {
+                Value::from_reader(id, &mut src).unwrap();
+            }
+        }
+    }
+    assert_eq!(found, 1);
+
+    // The declared length must be available in full.
+    let short = vec![0x00, 0x00, 0x00, 0x03, 0x01, 0x02];
+    assert_eq!(
+        raw::read_bare_byte_array(&mut io::Cursor::new(short), endian),
+        Err(Error::IncompleteNbtValue)
+    );
+}
+
+#[test]
+fn nbt_write_header() {
+    let endian = Endianness::BigEndian;
+    let mut dst = Vec::new();
+    raw::write_header(&mut dst, 0x03, "count", endian).unwrap();
+    assert_eq!(dst, vec![0x03, 0x00, 0x05, 0x63, 0x6f, 0x75, 0x6e, 0x74]);
+
+    let mut src = io::Cursor::new(dst);
+    assert_eq!(
+        raw::emit_next_header(&mut src, endian).unwrap(),
+        (0x03, "count".to_string())
+    );
+
+    let mut dst = Vec::new();
+    raw::write_header(&mut dst, 0x0a, "", Endianness::LittleEndian).unwrap();
+    assert_eq!(dst, vec![0x0a, 0x00, 0x00]);
+}
+
+#[test]
+fn nbt_to_payload_vec() {
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+
+    let mut map = Map::new();
+    map.insert("name".to_string(), Value::String("Herobrine".to_string()));
+    let payload = Value::Compound(map)
+        .to_payload_vec(Endianness::BigEndian)
+        .unwrap();
+    // Everything after the root tag and its empty name.
+    assert_eq!(&payload[..], &bytes[3..]);
+
+    assert_eq!(
+        Value::Int(1).to_payload_vec(Endianness::LittleEndian),
+        Ok(vec![0x01, 0x00, 0x00, 0x00])
+    );
+}
+
+#[test]
+fn nbt_read_list_header() {
+    let mut file = File::open("tests/small3.nbt").unwrap();
+    let endian = Endianness::BigEndian;
+    assert_eq!(
+        raw::emit_next_header(&mut file, endian).unwrap(),
+        (0x0a, "aaa".to_string())
+    );
+    assert_eq!(
+        raw::emit_next_header(&mut file, endian).unwrap(),
+        (0x09, "bbb".to_string())
+    );
+    // A list of two compounds.
+    assert_eq!(raw::read_list_header(&mut file, endian).unwrap(), (0x0a, 2));
+
+    // The elements themselves have not been consumed.
+    let first = Value::from_reader(0x0a, &mut file).unwrap();
+    assert_eq!(first.tag_name(), "TAG_Compound");
+}
+
 #[test]
 #[cfg(feature = "serde")]
 fn serde_blob() {
@@ -362,6 +859,37 @@ fn serde_blob() {
     assert_eq!(&bytes, &dst);
 }
 
+#[test]
+#[cfg(feature = "serde")]
+fn serde_named_blob() {
+    use ser::to_writer;
+
+    let mut nbt = Blob::named("Level");
+    nbt.insert("health", 100i8).unwrap();
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x05,
+            0x4c, 0x65, 0x76, 0x65, 0x6c,
+            0x01,
+                0x00, 0x06,
+                0x68, 0x65, 0x61, 0x6c, 0x74, 0x68,
+                0x64,
+        0x00
+    ];
+
+    let mut dst = Vec::new();
+    to_writer(&mut dst, &nbt, Some(nbt.title())).unwrap();
+    assert_eq!(&bytes, &dst);
+
+    // Without a header the blob is still written, with an empty name.
+    let mut dst = Vec::new();
+    to_writer(&mut dst, &nbt, None).unwrap();
+    assert_eq!(&dst[..3], &[0x0a, 0x00, 0x00]);
+    assert_eq!(&bytes[8..], &dst[3..]);
+}
+
 #[test]
 fn nbt_modified_utf8() {
     let mut nbt = Blob::new();
@@ -391,6 +919,26 @@ fn nbt_modified_utf8() {
     assert_eq!(&file, &nbt);
 }
 
+#[test]
+fn nbt_legacy_utf8() {
+    // Older versions of this crate wrote plain UTF-8, so supplementary
+    // characters are stored as four bytes rather than a surrogate pair.
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x08,
+                0x00, 0x01,
+                0x61,
+                0x00, 0x04,
+                0xf0, 0x90, 0x90, 0x81,
+        0x00
+    ];
+
+    let file = Blob::from_reader(&mut io::Cursor::new(bytes)).unwrap();
+    assert_eq!(file["a"], Value::String("\u{10401}".to_string()));
+}
+
 #[test]
 fn nbt_sizes() {
     // Arbitarary values, covering most data types
@@ -434,3 +982,1629 @@ fn nbt_sizes() {
 
     assert_eq!(cursor.position() as usize, root.len_bytes());
 }
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn nbt_sort_compounds() {
+    let mut inner_one = Map::new();
+    inner_one.insert("b".to_string(), Value::Short(2));
+    inner_one.insert("a".to_string(), Value::Byte(1));
+    let mut one = Map::new();
+    one.insert(
+        "z".to_string(),
+        Value::List(vec![Value::Compound(inner_one)]),
+    );
+    one.insert("y".to_string(), Value::Int(3));
+
+    let mut inner_two = Map::new();
+    inner_two.insert("a".to_string(), Value::Byte(1));
+    inner_two.insert("b".to_string(), Value::Short(2));
+    let mut two = Map::new();
+    two.insert("y".to_string(), Value::Int(3));
+    two.insert(
+        "z".to_string(),
+        Value::List(vec![Value::Compound(inner_two)]),
+    );
+
+    let mut one = Value::Compound(one);
+    let mut two = Value::Compound(two);
+
+    let (mut dst_one, mut dst_two) = (Vec::new(), Vec::new());
+    one.to_writer(&mut dst_one).unwrap();
+    two.to_writer(&mut dst_two).unwrap();
+    assert_ne!(&dst_one, &dst_two);
+
+    one.sort_compounds();
+    two.sort_compounds();
+
+    let (mut dst_one, mut dst_two) = (Vec::new(), Vec::new());
+    one.to_writer(&mut dst_one).unwrap();
+    two.to_writer(&mut dst_two).unwrap();
+    assert_eq!(&dst_one, &dst_two);
+}
+
+#[test]
+fn nbt_annotate() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x06,
+                0x68, 0x65, 0x61, 0x6c, 0x74, 0x68,
+                0x64,
+            0x09,
+                0x00, 0x04,
+                0x6c, 0x69, 0x73, 0x74,
+                0x02,
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x01,
+        0x00
+    ];
+
+    let listing = annotate(&bytes).unwrap();
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[0], "offset 0: 0x0a TAG_Compound");
+    assert_eq!(lines[1], "offset 1: name \"\"");
+    assert_eq!(lines[2], "offset 3:   0x01 TAG_Byte");
+    assert_eq!(lines[3], "offset 4:   name \"health\"");
+    assert_eq!(lines[4], "offset 12:   value 100");
+    assert_eq!(lines[7], "offset 20:   1 entries of type 0x02 TAG_Short");
+    assert_eq!(lines[8], "offset 25:     value 1");
+    assert_eq!(lines[9], "offset 27:   0x00 TAG_End");
+
+    // Stop with context at the first error.
+    let listing = annotate(&bytes[..24]).unwrap();
+    assert_eq!(
+        listing.lines().last(),
+        Some("offset 24: error: data does not represent a complete NbtValue")
+    );
+}
+
+#[test]
+fn nbt_annotate_depth_limit() {
+    let bytes = nested_lists(1000);
+    let listing = annotate(&bytes).unwrap();
+    let last = listing.lines().last().unwrap();
+    assert!(last.ends_with("error: compounds and lists are nested too deeply"));
+}
+
+#[test]
+#[cfg(feature = "serde_json")]
+fn nbt_to_json() {
+    let mut map = Map::new();
+    map.insert("ints".to_string(), Value::IntArray(vec![1, 2]));
+    map.insert(
+        "list".to_string(),
+        Value::List(vec![Value::Byte(3), Value::Byte(4)]),
+    );
+    let nbt = Value::Compound(map);
+
+    assert_eq!(
+        nbt.to_json(false),
+        ::serde_json::json!({ "ints": [1, 2], "list": [3, 4] })
+    );
+    assert_eq!(
+        nbt.to_json(true),
+        ::serde_json::json!({
+            "ints": { "int_array": [1, 2] },
+            "list": { "list": [3, 4] },
+        })
+    );
+}
+
+#[test]
+fn nbt_element_count() {
+    let mut map = Map::new();
+    map.insert("a".to_string(), Value::Byte(1));
+    assert_eq!(Value::Compound(map).element_count(), Some(1));
+    assert_eq!(
+        Value::List(vec![Value::Int(1), Value::Int(2)]).element_count(),
+        Some(2)
+    );
+    assert_eq!(Value::ByteArray(vec![1, 2, 3]).element_count(), Some(3));
+    assert_eq!(Value::IntArray(vec![]).element_count(), Some(0));
+    assert_eq!(Value::LongArray(vec![1]).element_count(), Some(1));
+    // Characters, not bytes.
+    assert_eq!(Value::String("héllo".to_string()).element_count(), Some(5));
+    assert_eq!(Value::Double(1.0).element_count(), None);
+}
+
+#[test]
+fn nbt_as_bool() {
+    assert_eq!(Value::Byte(0).as_bool(), Some(false));
+    assert_eq!(Value::Byte(1).as_bool(), Some(true));
+    assert_eq!(Value::Byte(2).as_bool(), None);
+    assert_eq!(Value::Int(1).as_bool(), None);
+}
+
+#[test]
+fn nbt_map_strings() {
+    let mut display = Map::new();
+    display.insert("Name".to_string(), Value::String("Sword".to_string()));
+    display.insert(
+        "Lore".to_string(),
+        Value::List(vec![Value::String("sharp".to_string())]),
+    );
+    display.insert("color".to_string(), Value::Int(42));
+    let mut nbt = Value::Compound(display);
+
+    nbt.map_strings(|s| s.to_uppercase());
+
+    let mut expected = Map::new();
+    expected.insert("Name".to_string(), Value::String("SWORD".to_string()));
+    expected.insert(
+        "Lore".to_string(),
+        Value::List(vec![Value::String("SHARP".to_string())]),
+    );
+    expected.insert("color".to_string(), Value::Int(42));
+    assert_eq!(nbt, Value::Compound(expected));
+}
+
+#[test]
+fn nbt_retain_recursive() {
+    let mut tag = Map::new();
+    tag.insert("CustomModelData".to_string(), Value::Int(1));
+    tag.insert("Damage".to_string(), Value::Int(5));
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::String("sword".to_string()));
+    item.insert("tag".to_string(), Value::Compound(tag));
+    let mut root = Map::new();
+    root.insert("CustomModelData".to_string(), Value::Int(2));
+    root.insert(
+        "Items".to_string(),
+        Value::List(vec![Value::Compound(item)]),
+    );
+    let mut nbt = Value::Compound(root);
+
+    nbt.retain_recursive(|name, _| name != "CustomModelData");
+
+    let mut tag = Map::new();
+    tag.insert("Damage".to_string(), Value::Int(5));
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::String("sword".to_string()));
+    item.insert("tag".to_string(), Value::Compound(tag));
+    let mut root = Map::new();
+    root.insert(
+        "Items".to_string(),
+        Value::List(vec![Value::Compound(item)]),
+    );
+    assert_eq!(nbt, Value::Compound(root));
+}
+
+#[test]
+fn nbt_collect_scalars() {
+    let mut pos = Map::new();
+    pos.insert("y".to_string(), Value::Double(64.5));
+    let nbt = Value::List(vec![
+        Value::List(vec![Value::Byte(1), Value::Byte(-2)]),
+        Value::List(vec![Value::Long(3)]),
+        Value::List(vec![Value::String("skipped".to_string())]),
+        Value::List(vec![Value::IntArray(vec![4, 5]), Value::IntArray(vec![])]),
+        Value::List(vec![Value::Compound(pos)]),
+    ]);
+    assert_eq!(nbt.collect_scalars(), vec![1.0, -2.0, 3.0, 4.0, 5.0, 64.5]);
+    assert!(Value::List(vec![]).collect_scalars().is_empty());
+}
+
+#[test]
+fn nbt_array_accessors() {
+    let mut states = Value::LongArray(vec![1, 2, 3]);
+    assert_eq!(states.as_long_array(), Some(&[1i64, 2, 3][..]));
+    assert_eq!(states.as_int_array(), None);
+    assert_eq!(states.as_byte_array(), None);
+
+    // Mutate the array in place.
+    {
+        let vals = states.as_long_array_mut().unwrap();
+        vals[1] = -1;
+        vals.push(4);
+    }
+    assert_eq!(states, Value::LongArray(vec![1, -1, 3, 4]));
+
+    let mut ints = Value::IntArray(vec![5, 6]);
+    ints.as_int_array_mut().unwrap()[0] = 7;
+    assert_eq!(ints.as_int_array(), Some(&[7, 6][..]));
+    assert_eq!(ints.as_long_array_mut(), None);
+
+    let mut bytes = Value::ByteArray(vec![8]);
+    bytes.as_byte_array_mut().unwrap().clear();
+    assert_eq!(bytes.as_byte_array(), Some(&[][..]));
+    assert_eq!(Value::Int(1).as_byte_array(), None);
+}
+
+#[test]
+fn nbt_add_i64() {
+    let mut byte = Value::Byte(1);
+    byte.add_i64(2).unwrap();
+    assert_eq!(byte, Value::Byte(3));
+    assert_eq!(byte.add_i64(125), Err(Error::Overflow));
+    assert_eq!(byte, Value::Byte(3));
+    byte.add_i64(124).unwrap();
+    assert_eq!(byte, Value::Byte(127));
+
+    let mut short = Value::Short(-1);
+    short.add_i64(-32767).unwrap();
+    assert_eq!(short, Value::Short(i16::MIN));
+    assert_eq!(short.add_i64(-1), Err(Error::Overflow));
+
+    let mut int = Value::Int(100);
+    int.add_i64(-200).unwrap();
+    assert_eq!(int, Value::Int(-100));
+    assert_eq!(int.add_i64(i64::from(i32::MAX)), Ok(()));
+    assert_eq!(int.add_i64(101), Err(Error::Overflow));
+
+    let mut long = Value::Long(i64::MAX - 1);
+    long.add_i64(1).unwrap();
+    assert_eq!(long, Value::Long(i64::MAX));
+    assert_eq!(long.add_i64(1), Err(Error::Overflow));
+    assert_eq!(long.add_i64(i64::MIN), Ok(()));
+    assert_eq!(long, Value::Long(-1));
+
+    let mut float = Value::Float(1.0);
+    assert_eq!(float.add_i64(1), Err(Error::TagMismatch(0x05, 0x04)));
+}
+
+#[test]
+fn nbt_long_array_bits() {
+    // Sixteen block states at 4 bits per entry fill exactly one long.
+    let states: Vec<u32> = (0..16).collect();
+    let longs = LongArrayBits::pack(&states, 4);
+    assert_eq!(longs, vec![0xfedc_ba98_7654_3210u64 as i64]);
+    assert_eq!(LongArrayBits::new(&longs).unpack(4), states);
+
+    // At 5 bits per entry only 12 entries fit in each long, leaving the top
+    // 4 bits unused, and the last long is padded with zeroes.
+    let states: Vec<u32> = (0..30).collect();
+    let longs = LongArrayBits::pack(&states, 5);
+    assert_eq!(longs.len(), 3);
+    assert_eq!(longs[0], 0x05a9_2839_8a41_8820);
+    assert_eq!(longs[1], 0x0bda_b49c_a307_b9ac);
+    let unpacked = LongArrayBits::new(&longs).unpack(5);
+    assert_eq!(unpacked.len(), 36);
+    assert_eq!(&unpacked[..30], &states[..]);
+    assert!(unpacked[30..].iter().all(|&v| v == 0));
+
+    let value = Value::LongArray(longs);
+    let view = LongArrayBits::new(value.as_long_array().unwrap());
+    assert_eq!(&view.unpack(5)[..30], &states[..]);
+}
+
+/// Builds a document whose root holds `depth` lists nested inside each other.
+fn nested_lists(depth: usize) -> Vec<u8> {
+    let mut bytes = vec![0x0a, 0x00, 0x00, 0x09, 0x00, 0x00];
+    for _ in 1..depth {
+        bytes.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01]);
+    }
+    bytes.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+    bytes.push(0x00);
+    bytes
+}
+
+#[test]
+fn nbt_depth_limit() {
+    let bytes = nested_lists(1000);
+    assert_eq!(
+        Blob::from_reader(&mut io::Cursor::new(&bytes)),
+        Err(Error::DepthLimitExceeded)
+    );
+
+    // Compounds and lists share the same limit, which counts the root.
+    let bytes = nested_lists(3);
+    let mut options = ReadOptions {
+        max_depth: 4,
+        ..Default::default()
+    };
+    assert!(Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options).is_ok());
+    options.max_depth = 3;
+    assert_eq!(
+        Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options),
+        Err(Error::DepthLimitExceeded)
+    );
+
+    let mut bytes = vec![0x0a, 0x00, 0x00];
+    for _ in 0..1000 {
+        bytes.extend_from_slice(&[0x0a, 0x00, 0x00]);
+    }
+    bytes.extend(vec![0x00; 1001]);
+    assert_eq!(
+        Blob::from_reader(&mut io::Cursor::new(&bytes)),
+        Err(Error::DepthLimitExceeded)
+    );
+}
+
+#[test]
+fn nbt_heightmap() {
+    use chunk::{decode_heightmap, encode_heightmap, heightmap_bits};
+
+    assert_eq!(heightmap_bits(256), 9);
+    assert_eq!(heightmap_bits(384), 9);
+    assert_eq!(heightmap_bits(255), 8);
+
+    // A flat world at height 64 packs seven 9-bit heights into each long,
+    // leaving four in the last.
+    let mut longs = vec![0x1008_0402_0100_8040; 37];
+    longs[36] = 0x2_0100_8040;
+    let heights = decode_heightmap(&Value::LongArray(longs.clone()), 256).unwrap();
+    assert_eq!(heights, vec![64; 256]);
+    assert_eq!(encode_heightmap(&heights, 256), Value::LongArray(longs));
+
+    let heights: Vec<u16> = (0..256).map(|h| h as u16 + 100).collect();
+    let encoded = encode_heightmap(&heights, 384);
+    assert_eq!(decode_heightmap(&encoded, 384).unwrap(), heights);
+
+    assert_eq!(
+        decode_heightmap(&Value::LongArray(vec![0; 36]), 256),
+        Err(Error::IncompleteNbtValue)
+    );
+    assert_eq!(
+        decode_heightmap(&Value::IntArray(vec![]), 256),
+        Err(Error::TagMismatch(0x0b, 0x0c))
+    );
+}
+
+#[test]
+fn nbt_read_named() {
+    let mut file = File::open("tests/small2.nbt").unwrap();
+    let (name, value) = Value::read_named(&mut file, Endianness::BigEndian).unwrap();
+    assert_eq!(name, "hello world");
+    match value {
+        Value::Compound(ref map) => {
+            assert_eq!(map.len(), 2);
+            let mut aaa = Map::new();
+            aaa.insert("1".to_string(), Value::Byte(0x11));
+            aaa.insert("2".to_string(), Value::Short(0x1122));
+            aaa.insert("3".to_string(), Value::Int(0x1122_3344));
+            assert_eq!(map["aaa"], Value::Compound(aaa.clone()));
+            assert_eq!(map["bbb"], Value::Compound(aaa));
+        }
+        _ => panic!("expected a compound, found {:?}", value),
+    }
+
+    // The root does not have to be a compound.
+    let bytes = [0x03, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00, 0x2a];
+    let (name, value) = Value::read_named(&mut &bytes[..], Endianness::BigEndian).unwrap();
+    assert_eq!(name, "a");
+    assert_eq!(value, Value::Int(42));
+}
+
+#[test]
+fn nbt_write_subtree() {
+    let mut player = Map::new();
+    player.insert("foodLevel".to_string(), Value::Int(20));
+    player.insert("Health".to_string(), Value::Float(20.0));
+    let mut data = Map::new();
+    data.insert("Player".to_string(), Value::Compound(player.clone()));
+    data.insert("Time".to_string(), Value::Long(1000));
+    let mut level = Blob::new();
+    level.insert("Data", Value::Compound(data)).unwrap();
+
+    assert_eq!(level.get_path("Data.Time"), Some(&Value::Long(1000)));
+    assert_eq!(level.get_path("Data.Time.Nope"), None);
+
+    let mut dst = Vec::new();
+    level.write_subtree(&mut dst, "Data.Player").unwrap();
+    let extracted = Blob::from_reader(&mut io::Cursor::new(dst)).unwrap();
+    assert_eq!(extracted.title(), "Player");
+    assert_eq!(extracted["foodLevel"], Value::Int(20));
+    assert_eq!(extracted["Health"], Value::Float(20.0));
+
+    // Other values are wrapped in a compound.
+    let mut dst = Vec::new();
+    level.write_subtree(&mut dst, "Data.Time").unwrap();
+    let extracted = Blob::from_reader(&mut io::Cursor::new(dst)).unwrap();
+    assert_eq!(extracted.title(), "");
+    assert_eq!(extracted["Time"], Value::Long(1000));
+
+    assert_eq!(
+        level.write_subtree(&mut Vec::new(), "Data.Missing"),
+        Err(Error::PathNotFound("Data.Missing".to_string()))
+    );
+}
+
+#[test]
+#[cfg(all(feature = "serde", feature = "preserve_order"))]
+fn serde_blob_preserves_order() {
+    use de::from_reader;
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x01, 0x7a,
+                0x01,
+            0x0a,
+                0x00, 0x01, 0x63,
+                0x01,
+                    0x00, 0x01, 0x79,
+                    0x02,
+                0x01,
+                    0x00, 0x01, 0x62,
+                    0x03,
+                0x00,
+            0x01,
+                0x00, 0x01, 0x61,
+                0x04,
+        0x00
+    ];
+
+    let blob: Blob = from_reader(&bytes[..]).unwrap();
+    match blob["c"] {
+        Value::Compound(ref map) => {
+            assert_eq!(map.keys().collect::<Vec<_>>(), vec!["y", "b"]);
+        }
+        ref other => panic!("expected a compound, found {:?}", other),
+    }
+    let mut dst = Vec::new();
+    blob.to_writer(&mut dst).unwrap();
+    assert_eq!(&bytes, &dst);
+}
+
+#[test]
+fn nbt_max_nodes() {
+    let mut nbt = Blob::new();
+    for i in 0..100 {
+        nbt.insert(format!("entry{}", i), i as i8).unwrap();
+    }
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+
+    // The root compound counts as a tag, along with each of its entries.
+    let options = ReadOptions {
+        max_nodes: 101,
+        ..Default::default()
+    };
+    assert_eq!(
+        Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options),
+        Ok(nbt)
+    );
+    let options = ReadOptions {
+        max_nodes: 100,
+        ..Default::default()
+    };
+    assert_eq!(
+        Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options),
+        Err(Error::SizeLimitExceeded)
+    );
+
+    // List elements are counted as soon as the list's length is read.
+    let mut nbt = Blob::new();
+    nbt.insert("list", Value::List(vec![Value::Byte(0); 1000]))
+        .unwrap();
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+    assert_eq!(
+        Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options),
+        Err(Error::SizeLimitExceeded)
+    );
+}
+
+#[test]
+fn nbt_tag_name_for_id() {
+    let names = [
+        "TAG_End",
+        "TAG_Byte",
+        "TAG_Short",
+        "TAG_Int",
+        "TAG_Long",
+        "TAG_Float",
+        "TAG_Double",
+        "TAG_ByteArray",
+        "TAG_String",
+        "TAG_List",
+        "TAG_Compound",
+        "TAG_IntArray",
+        "TAG_LongArray",
+    ];
+    for (id, &name) in names.iter().enumerate() {
+        assert_eq!(tag_name_for_id(id as u8), Some(name));
+    }
+    assert_eq!(tag_name_for_id(0x0d), None);
+    assert_eq!(Value::LongArray(vec![]).tag_name(), "TAG_LongArray");
+
+    assert_eq!(
+        Error::TagMismatch(0x03, 0x01).to_string(),
+        "encountered NBT tag '3' (TAG_Int) but expected '1' (TAG_Byte)"
+    );
+    assert_eq!(
+        Error::InvalidTypeId(0x0d).to_string(),
+        "invalid NBT tag byte: '13'"
+    );
+}
+
+#[test]
+fn nbt_raw_writer_reader() {
+    use raw::{RawReader, RawWriter};
+
+    for &endian in &[Endianness::BigEndian, Endianness::LittleEndian] {
+        let mut dst = RawWriter::new(Vec::new(), endian);
+        dst.write_byte(-1).unwrap();
+        dst.write_short(0x0102).unwrap();
+        dst.write_int(0x0102_0304).unwrap();
+        dst.write_long(0x0102_0304_0506_0708).unwrap();
+        dst.write_float(1.5).unwrap();
+        dst.write_double(-2.25).unwrap();
+        dst.write_byte_array(&[1, -2]).unwrap();
+        dst.write_int_array(&[3, -4]).unwrap();
+        dst.write_long_array(&[5, -6]).unwrap();
+        dst.write_string("\u{10401}").unwrap();
+        dst.write_header(0x09, "list").unwrap();
+        dst.write_byte(0x03).unwrap();
+        dst.write_int(0).unwrap();
+        dst.close_nbt().unwrap();
+        let bytes = dst.into_inner();
+
+        // The free functions agree on the encoding of each value.
+        let mut expected = Vec::new();
+        raw::write_bare_byte(&mut expected, -1).unwrap();
+        raw::write_bare_short(&mut expected, 0x0102, endian).unwrap();
+        assert_eq!(&bytes[..3], &expected[..]);
+        match endian {
+            Endianness::BigEndian => assert_eq!(&bytes[1..3], &[0x01, 0x02]),
+            Endianness::LittleEndian => assert_eq!(&bytes[1..3], &[0x02, 0x01]),
+        }
+
+        let mut src = RawReader::new(&bytes[..], endian);
+        assert_eq!(src.read_byte().unwrap(), -1);
+        assert_eq!(src.read_short().unwrap(), 0x0102);
+        assert_eq!(src.read_int().unwrap(), 0x0102_0304);
+        assert_eq!(src.read_long().unwrap(), 0x0102_0304_0506_0708);
+        assert_eq!(src.read_float().unwrap(), 1.5);
+        assert_eq!(src.read_double().unwrap(), -2.25);
+        assert_eq!(src.read_byte_array().unwrap(), vec![1, -2]);
+        assert_eq!(src.read_int_array().unwrap(), vec![3, -4]);
+        assert_eq!(src.read_long_array().unwrap(), vec![5, -6]);
+        assert_eq!(src.read_string().unwrap(), "\u{10401}");
+        assert_eq!(src.read_header().unwrap(), (0x09, "list".to_string()));
+        assert_eq!(src.read_list_header().unwrap(), (0x03, 0));
+        assert_eq!(src.read_header().unwrap(), (0x00, "".to_string()));
+        assert!(src.into_inner().is_empty());
+    }
+}
+
+#[test]
+fn nbt_find_strings() {
+    let mut display = Map::new();
+    display.insert("Name".to_string(), Value::from("item.sword.name"));
+    display.insert(
+        "Lore".to_string(),
+        Value::List(vec![Value::from("plain"), Value::from("item.sword.lore")]),
+    );
+    let mut tag = Map::new();
+    tag.insert("display".to_string(), Value::Compound(display));
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::from("minecraft:sword"));
+    item.insert("tag".to_string(), Value::Compound(tag));
+
+    let mut nbt = Blob::new();
+    nbt.insert("Items", Value::List(vec![Value::Compound(item)]))
+        .unwrap();
+    nbt.insert("title", "item.chest.name").unwrap();
+    nbt.insert("count", 1i8).unwrap();
+
+    let mut found = nbt.find_strings(|s| s.starts_with("item."));
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            ("Items.0.tag.display.Lore.1".to_string(), "item.sword.lore"),
+            ("Items.0.tag.display.Name".to_string(), "item.sword.name"),
+            ("title".to_string(), "item.chest.name"),
+        ]
+    );
+
+    // Paths from a value are relative to it, and it can be matched itself.
+    let lore = Value::List(vec![Value::from("a"), Value::from("b")]);
+    assert_eq!(
+        lore.find_strings(|s| s == "b"),
+        vec![("1".to_string(), "b")]
+    );
+    assert_eq!(
+        Value::from("a").find_strings(|_| true),
+        vec![("".to_string(), "a")]
+    );
+
+    let mut paths = Vec::new();
+    nbt.walk(|path, _| paths.push(path.to_string()));
+    assert_eq!(paths.len(), 11);
+}
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn nbt_bedrock_roundtrip() {
+    use std::io::Read;
+
+    // A Bedrock block palette entry, which is little-endian and whose keys
+    // are deliberately not in sorted order.
+    let mut bytes = Vec::new();
+    File::open("tests/bedrock_palette.nbt")
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+
+    let options = ReadOptions {
+        endian: Endianness::LittleEndian,
+        ..Default::default()
+    };
+    let file = Blob::from_reader_with(&mut io::Cursor::new(&bytes), &options).unwrap();
+    assert_eq!(file["version"], Value::Int(17959425));
+    assert_eq!(file["heights"], Value::IntArray(vec![-64, 320]));
+    match file["states"] {
+        Value::Compound(ref states) => {
+            let keys: Vec<_> = states.keys().map(|k| &k[..]).collect();
+            assert_eq!(
+                keys,
+                vec![
+                    "stone_slab_type",
+                    "top_slot_bit",
+                    "pillar_axis",
+                    "age",
+                    "facing_direction",
+                ]
+            );
+        }
+        ref other => panic!("expected a compound, found {:?}", other),
+    }
+
+    let mut dst = Vec::new();
+    file.to_writer_endian(&mut dst, Endianness::LittleEndian)
+        .unwrap();
+    assert_eq!(bytes, dst);
+}
+
+#[test]
+fn nbt_le_reader_writer() {
+    let mut src = File::open("tests/bedrock_palette.nbt").unwrap();
+    let file = Blob::from_le_reader(&mut src).unwrap();
+    assert_eq!(file["version"], Value::Int(17959425));
+    assert_eq!(
+        file["name"],
+        Value::String("minecraft:smooth_stone_slab".into())
+    );
+
+    let mut dst = Vec::new();
+    file.to_le_writer(&mut dst).unwrap();
+    assert_eq!(dst.len(), file.len_bytes());
+    let read = Blob::from_le_reader(&mut io::Cursor::new(&dst)).unwrap();
+    assert_eq!(read, file);
+
+    // The same data is garbled when read as big-endian.
+    assert!(Blob::from_reader(&mut io::Cursor::new(&dst)).is_err());
+}
+
+#[test]
+fn nbt_write_counted() {
+    let mut src = File::open("tests/big1.nbt").unwrap();
+    let nbt = Blob::from_gzip_reader(&mut src).unwrap();
+    let mut expected = Vec::new();
+    nbt.to_writer(&mut expected).unwrap();
+
+    let mut dst = Vec::new();
+    assert_eq!(nbt.write_counted(&mut dst).unwrap(), expected.len());
+    assert_eq!(dst, expected);
+    assert_eq!(Blob::new().write_counted(&mut io::sink()).unwrap(), 4);
+}
+
+#[test]
+fn nbt_varint_network() {
+    let endian = raw::Format::NETWORK;
+    let mut dst = Vec::new();
+    raw::write_bare_int(&mut dst, -1, endian).unwrap();
+    raw::write_bare_int(&mut dst, 300, endian).unwrap();
+    raw::write_bare_long(&mut dst, i64::MIN, endian).unwrap();
+    assert_eq!(&dst[..3], &[0x01, 0xd8, 0x04]);
+    assert_eq!(dst.len(), 3 + 10);
+
+    let mut src = io::Cursor::new(&dst);
+    assert_eq!(raw::read_bare_int(&mut src, endian).unwrap(), -1);
+    assert_eq!(raw::read_bare_int(&mut src, endian).unwrap(), 300);
+    assert_eq!(raw::read_bare_long(&mut src, endian).unwrap(), i64::MIN);
+
+    // Strings and arrays longer than 127 elements have multi-byte lengths.
+    let name = "x".repeat(200);
+    let mut nbt = Blob::new();
+    nbt.insert(name.clone(), Value::IntArray(vec![-2; 200]))
+        .unwrap();
+    let mut dst = Vec::new();
+    nbt.to_varint_writer(&mut dst).unwrap();
+    assert_eq!(&dst[..6], &[0x0a, 0x00, 0x0b, 0xc8, 0x01, 0x78]);
+    assert_eq!(&dst[205..208], &[0x90, 0x03, 0x03]);
+
+    nbt.insert("health", Value::Short(-20)).unwrap();
+    let mut dst = Vec::new();
+    nbt.to_varint_writer(&mut dst).unwrap();
+    let read = Blob::from_varint_reader(&mut io::Cursor::new(&dst)).unwrap();
+    assert_eq!(read, nbt);
+
+    // Too many continuation bytes are refused rather than read forever.
+    assert_eq!(
+        raw::read_bare_int(&mut &[0xff; 6][..], endian),
+        Err(Error::VarIntTooLong(5))
+    );
+    assert_eq!(
+        raw::read_bare_long(&mut &[0x80; 11][..], endian),
+        Err(Error::VarIntTooLong(10))
+    );
+
+    // A string can't claim more bytes than the data holds, however long its
+    // length says it is.
+    assert_eq!(
+        raw::read_bare_string(&mut &[0xff, 0xff, 0xff, 0xff, 0x0f, 0x61][..], endian),
+        Err(Error::IncompleteNbtValue)
+    );
+
+    // The same data can be read by setting the flavor in `ReadOptions`.
+    let options = ReadOptions {
+        endian: Endianness::LittleEndian,
+        flavor: raw::Flavor::VarInt,
+        ..Default::default()
+    };
+    let read = Blob::from_reader_with(&mut io::Cursor::new(&dst), &options).unwrap();
+    assert_eq!(read, nbt);
+}
+
+#[test]
+fn nbt_from_reader_expect_name() {
+    let mut nbt = Blob::named("Level");
+    nbt.insert("health", 100i8).unwrap();
+    let mut bytes = Vec::new();
+    nbt.to_writer(&mut bytes).unwrap();
+
+    let read = Blob::from_reader_expect_name(&mut io::Cursor::new(&bytes), "Level").unwrap();
+    assert_eq!(read, nbt);
+
+    let err = Blob::from_reader_expect_name(&mut io::Cursor::new(&bytes), "").unwrap_err();
+    assert_eq!(
+        err,
+        Error::UnexpectedRootName(Box::new(RootNames {
+            found: "Level".to_string(),
+            expected: "".to_string(),
+        }))
+    );
+    assert_eq!(
+        err.to_string(),
+        "root compound is named 'Level' but expected ''"
+    );
+}
+
+#[test]
+fn nbt_array_be_bytes() {
+    let ints = Value::IntArray(vec![1, -2]);
+    let bytes = ints.to_be_bytes().unwrap();
+    assert_eq!(bytes, vec![0, 0, 0, 1, 0xff, 0xff, 0xff, 0xfe]);
+    assert_eq!(Value::int_array_from_be_bytes(&bytes), Ok(ints));
+
+    // The same bytes can be read as a single long.
+    let longs = Value::long_array_from_be_bytes(&bytes).unwrap();
+    assert_eq!(longs, Value::LongArray(vec![0x0000_0001_ffff_fffe]));
+    assert_eq!(longs.to_be_bytes(), Some(bytes));
+
+    let longs = Value::LongArray(vec![i64::MIN, 7]);
+    let bytes = longs.to_be_bytes().unwrap();
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(Value::long_array_from_be_bytes(&bytes), Ok(longs));
+
+    assert_eq!(
+        Value::int_array_from_be_bytes(&[0; 6]),
+        Err(Error::IncompleteNbtValue)
+    );
+    assert_eq!(
+        Value::long_array_from_be_bytes(&[0; 12]),
+        Err(Error::IncompleteNbtValue)
+    );
+    assert_eq!(
+        Value::int_array_from_be_bytes(&[]),
+        Ok(Value::IntArray(vec![]))
+    );
+    assert_eq!(Value::Int(1).to_be_bytes(), None);
+}
+
+#[test]
+fn nbt_into_list() {
+    assert_eq!(Value::Int(5).into_list(), Value::List(vec![Value::Int(5)]));
+    assert_eq!(
+        Value::String("a".to_string()).into_list(),
+        Value::List(vec![Value::String("a".to_string())])
+    );
+
+    let list = Value::List(vec![Value::Short(1), Value::Short(2)]);
+    assert_eq!(list.clone().into_list(), list);
+    assert_eq!(Value::List(vec![]).into_list(), Value::List(vec![]));
+}
+
+#[test]
+fn nbt_merge_strict() {
+    let mut player = Map::new();
+    player.insert("Health".to_string(), Value::Float(20.0));
+    player.insert("XpLevel".to_string(), Value::Int(3));
+    let mut nbt = Blob::new();
+    nbt.insert("Player", Value::Compound(player)).unwrap();
+    nbt.insert("Version", 19133i32).unwrap();
+
+    let mut update = Map::new();
+    update.insert("XpLevel".to_string(), Value::Int(4));
+    update.insert("Score".to_string(), Value::Int(10));
+    let mut other = Blob::new();
+    other.insert("Player", Value::Compound(update)).unwrap();
+    other.insert("DataVersion", 2586i32).unwrap();
+
+    nbt.merge_strict(&other).unwrap();
+    assert_eq!(nbt.get_path("Player.Health"), Some(&Value::Float(20.0)));
+    assert_eq!(nbt.get_path("Player.XpLevel"), Some(&Value::Int(4)));
+    assert_eq!(nbt.get_path("Player.Score"), Some(&Value::Int(10)));
+    assert_eq!(nbt["Version"], Value::Int(19133));
+    assert_eq!(nbt["DataVersion"], Value::Int(2586));
+
+    let mut conflict = Map::new();
+    conflict.insert("Health".to_string(), Value::Double(20.0));
+    let mut other = Blob::new();
+    other.insert("Version", 1i32).unwrap();
+    other.insert("Player", Value::Compound(conflict)).unwrap();
+
+    let before = nbt.clone();
+    assert_eq!(
+        nbt.merge_strict(&other),
+        Err(Error::TagMismatch(0x06, 0x05))
+    );
+    assert_eq!(nbt, before);
+}
+
+#[test]
+fn nbt_string_cesu8_bytes() {
+    let nbt = Value::String("\u{10401}\0".to_string());
+    let bytes = nbt.string_to_cesu8_bytes().unwrap();
+    assert_eq!(bytes, vec![0xed, 0xa0, 0x81, 0xed, 0xb0, 0x81, 0xc0, 0x80]);
+    assert_eq!(Value::string_from_cesu8(&bytes), Ok(nbt));
+
+    assert_eq!(Value::Int(1).string_to_cesu8_bytes(), None);
+    assert_eq!(
+        Value::string_from_cesu8(&[0x61, 0xff]),
+        Err(Error::InvalidUtf8)
+    );
+}
+
+#[test]
+fn nbt_all_paths() {
+    let mut file = File::open("tests/small2.nbt").unwrap();
+    let nbt = Blob::from_reader(&mut file).unwrap();
+    let mut paths = nbt.all_paths();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec!["aaa", "aaa.1", "aaa.2", "aaa.3", "bbb", "bbb.1", "bbb.2", "bbb.3"]
+    );
+
+    let mut nbt = Blob::new();
+    nbt.insert(
+        "Pos",
+        Value::List(vec![Value::Double(0.5), Value::Double(64.0)]),
+    )
+    .unwrap();
+    let mut paths = nbt.all_paths();
+    paths.sort();
+    assert_eq!(paths, vec!["Pos", "Pos.0", "Pos.1"]);
+    assert_eq!(nbt.get_path("Pos.1"), Some(&Value::Double(64.0)));
+    assert_eq!(nbt.get_path("Pos.2"), None);
+
+    // Every path can be looked up again, including those into lists.
+    let mut file = File::open("tests/complex_player.dat").unwrap();
+    let nbt = Blob::from_gzip_reader(&mut file).unwrap();
+    for path in nbt.all_paths() {
+        assert!(nbt.get_path(&path).is_some(), "{} not found", path);
+    }
+}
+
+#[test]
+fn nbt_to_named_writer() {
+    let mut dst = Vec::new();
+    Value::Int(42)
+        .to_named_writer(&mut dst, "a", Endianness::BigEndian)
+        .unwrap();
+    assert_eq!(dst, vec![0x03, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00, 0x2a]);
+
+    let mut src = &dst[..];
+    let (tag, name) = raw::emit_next_header(&mut src, Endianness::BigEndian).unwrap();
+    assert_eq!((tag, &name[..]), (0x03, "a"));
+    assert_eq!(
+        raw::read_bare_int(&mut src, Endianness::BigEndian).unwrap(),
+        42
+    );
+
+    let mut dst = Vec::new();
+    Value::Short(1)
+        .to_named_writer(&mut dst, "b", Endianness::LittleEndian)
+        .unwrap();
+    assert_eq!(
+        Value::read_named(&mut &dst[..], Endianness::LittleEndian).unwrap(),
+        ("b".to_string(), Value::Short(1))
+    );
+}
+
+/// A writer which fails once more than `limit` bytes have been written.
+struct LimitedWriter {
+    written: usize,
+    limit: usize,
+}
+
+impl io::Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.limit {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "writer is full"));
+        }
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn nbt_compressed_write_errors() {
+    let mut nbt = Blob::new();
+    nbt.insert("name", "Herobrine").unwrap();
+
+    // Compressed data is only written out when the encoder is finished, past
+    // the 10-byte Gzip header.
+    let mut dst = LimitedWriter {
+        written: 0,
+        limit: 10,
+    };
+    assert!(nbt.to_gzip_writer(&mut dst).is_err());
+
+    let mut dst = LimitedWriter {
+        written: 0,
+        limit: 10,
+    };
+    assert!(nbt.to_zlib_writer(&mut dst).is_err());
+
+    let mut dst = LimitedWriter {
+        written: 0,
+        limit: 1024,
+    };
+    nbt.to_gzip_writer(&mut dst).unwrap();
+}
+
+#[test]
+fn nbt_array_iter() {
+    let ints = Value::IntArray(vec![1, 2, 3, i32::MAX]);
+    let sum: i64 = ints.int_array_iter().unwrap().map(i64::from).sum();
+    assert_eq!(sum, 6 + i64::from(i32::MAX));
+
+    let longs = Value::LongArray(vec![-1, 1]);
+    assert_eq!(longs.long_array_iter().unwrap().sum::<i64>(), 0);
+
+    let bytes = Value::ByteArray(vec![1, -2]);
+    assert_eq!(
+        bytes.byte_array_iter().unwrap().collect::<Vec<_>>(),
+        vec![1, -2]
+    );
+
+    assert!(ints.long_array_iter().is_none());
+    assert!(Value::Int(1).int_array_iter().is_none());
+}
+
+#[test]
+fn nbt_read_into() {
+    let mut nbt = Blob::new();
+    nbt.read_into(&mut File::open("tests/small2.nbt").unwrap())
+        .unwrap();
+    assert_eq!(nbt.title(), "hello world");
+    assert!(nbt.get("aaa").is_some());
+
+    nbt.read_into(&mut File::open("tests/small1.nbt").unwrap())
+        .unwrap();
+    let expected = Blob::from_reader(&mut File::open("tests/small1.nbt").unwrap()).unwrap();
+    assert_eq!(nbt, expected);
+    assert!(nbt.get("aaa").is_none());
+}
+
+#[test]
+fn nbt_list_and_compound_mut() {
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::from("minecraft:stone"));
+    item.insert("Count".to_string(), Value::Byte(1));
+    let mut inventory = Value::List(vec![Value::Compound(item.clone())]);
+
+    let mut new_item = Value::Compound(item);
+    new_item
+        .as_compound_mut()
+        .unwrap()
+        .insert("Slot".to_string(), Value::Byte(1));
+    inventory.as_list_mut().unwrap().push(new_item.clone());
+
+    assert_eq!(inventory.element_count(), Some(2));
+    assert_eq!(inventory.as_list_mut().unwrap()[1], new_item);
+    assert!(inventory.validate().is_ok());
+
+    assert!(inventory.as_compound_mut().is_none());
+    assert!(Value::Int(1).as_list_mut().is_none());
+}
+
+#[test]
+fn nbt_compressed_endian_roundtrip() {
+    let mut nbt = Blob::named("palette");
+    nbt.insert("version", 17959425i32).unwrap();
+    nbt.insert("heights", vec![-64i32, 320]).unwrap();
+
+    let mut dst = Vec::new();
+    nbt.to_gzip_writer_endian(&mut dst, Endianness::LittleEndian)
+        .unwrap();
+    let file = Blob::from_gzip_reader_endian(&mut io::Cursor::new(&dst), Endianness::LittleEndian)
+        .unwrap();
+    assert_eq!(file, nbt);
+    // The data is not readable with the wrong byte order.
+    assert!(Blob::from_gzip_reader(&mut io::Cursor::new(&dst)).is_err());
+
+    let mut dst = Vec::new();
+    nbt.to_zlib_writer_endian(&mut dst, Endianness::LittleEndian)
+        .unwrap();
+    let file = Blob::from_zlib_reader_endian(&mut io::Cursor::new(&dst), Endianness::LittleEndian)
+        .unwrap();
+    assert_eq!(file, nbt);
+}
+
+#[test]
+fn nbt_partition_list_by() {
+    let item = |id: &str, enchanted: bool| {
+        let mut item = Map::new();
+        item.insert("id".to_string(), Value::from(id));
+        if enchanted {
+            let mut tag = Map::new();
+            tag.insert("Enchantments".to_string(), Value::List(vec![]));
+            item.insert("tag".to_string(), Value::Compound(tag));
+        }
+        Value::Compound(item)
+    };
+    let sword = item("minecraft:diamond_sword", true);
+    let stone = item("minecraft:stone", false);
+    let bow = item("minecraft:bow", true);
+    let items = Value::List(vec![sword.clone(), stone.clone(), bow.clone()]);
+
+    let (enchanted, plain) = items
+        .partition_list_by(|item| match *item {
+            Value::Compound(ref map) => map.contains_key("tag"),
+            _ => false,
+        })
+        .unwrap();
+    assert_eq!(enchanted, vec![&sword, &bow]);
+    assert_eq!(plain, vec![&stone]);
+
+    assert!(sword.partition_list_by(|_| true).is_none());
+}
+
+#[test]
+fn nbt_incomplete_root_name() {
+    // The root claims a 0x1f8b-byte name, as it would if Gzip data were read
+    // as uncompressed NBT, but nothing follows.
+    let bytes = vec![0x0a, 0x1f, 0x8b];
+    let err = Blob::from_reader(&mut io::Cursor::new(bytes)).unwrap_err();
+    assert_eq!(err, Error::IncompleteRootName);
+    assert!(err.to_string().contains("root compound"));
+
+    let bytes = vec![0x0a, 0x00];
+    assert_eq!(
+        Blob::from_reader(&mut io::Cursor::new(bytes)),
+        Err(Error::IncompleteRootName)
+    );
+
+    // Data ending after the name is still reported as incomplete.
+    let bytes = vec![0x0a, 0x00, 0x01, 0x61];
+    assert_eq!(
+        Blob::from_reader(&mut io::Cursor::new(bytes)),
+        Err(Error::IncompleteNbtValue)
+    );
+}
+
+#[test]
+fn nbt_rename_path() {
+    let mut player = Map::new();
+    player.insert("foodLevel".to_string(), Value::Int(20));
+    player.insert("Health".to_string(), Value::Float(20.0));
+    let mut data = Map::new();
+    data.insert("Player".to_string(), Value::Compound(player));
+    let mut nbt = Blob::new();
+    nbt.insert("Data", Value::Compound(data)).unwrap();
+
+    assert_eq!(
+        nbt.rename_path("Data.Player.foodLevel", "FoodLevel"),
+        Ok(true)
+    );
+    assert_eq!(nbt.get_path("Data.Player.FoodLevel"), Some(&Value::Int(20)));
+    assert_eq!(nbt.get_path("Data.Player.foodLevel"), None);
+    assert_eq!(
+        nbt.get_path("Data.Player.Health"),
+        Some(&Value::Float(20.0))
+    );
+
+    assert_eq!(nbt.rename_path("Data.Player.foodLevel", "Food"), Ok(false));
+    assert_eq!(nbt.rename_path("Data.Level.foodLevel", "Food"), Ok(false));
+    assert_eq!(
+        nbt.rename_path("Data.Player.FoodLevel", "Health"),
+        Err(Error::UnexpectedField("Health".to_string()))
+    );
+    assert_eq!(nbt.rename_path("Data", "data"), Ok(true));
+    assert!(nbt.get_path("data.Player.FoodLevel").is_some());
+
+    // Paths lead through lists as they do for `get_path`, but list elements
+    // have no names to change.
+    let mut item = Map::new();
+    item.insert(
+        "id".to_string(),
+        Value::String("minecraft:stone".to_string()),
+    );
+    nbt.insert("Inventory", Value::List(vec![Value::Compound(item)]))
+        .unwrap();
+    assert_eq!(nbt.rename_path("Inventory.0.id", "Id"), Ok(true));
+    assert!(nbt.get_path("Inventory.0.Id").is_some());
+    assert_eq!(nbt.rename_path("Inventory.0", "1"), Ok(false));
+}
+
+#[test]
+#[cfg(feature = "preserve_order")]
+fn nbt_rename_path_keeps_order() {
+    let mut nbt = Blob::new();
+    nbt.insert("a", 1i8).unwrap();
+    nbt.insert("b", 2i8).unwrap();
+    nbt.insert("c", 3i8).unwrap();
+    nbt.rename_path("b", "B").unwrap();
+    let paths = nbt.all_paths();
+    assert_eq!(paths, vec!["a", "B", "c"]);
+}
+
+#[test]
+fn nbt_size_delta() {
+    let mut before = Blob::new();
+    before.insert("xPos", 1i32).unwrap();
+    let mut after = before.clone();
+    after.insert("zPos", 2i32).unwrap();
+
+    // A TAG_Int entry named "zPos": tag, name length, name, and payload.
+    assert_eq!(after.size_delta(&before), 1 + 2 + 4 + 4);
+    assert_eq!(before.size_delta(&after), -11);
+    assert_eq!(before.size_delta(&before), 0);
+
+    let mut dst = Vec::new();
+    after.to_writer(&mut dst).unwrap();
+    let mut src = Vec::new();
+    before.to_writer(&mut src).unwrap();
+    assert_eq!(
+        after.size_delta(&before),
+        dst.len() as isize - src.len() as isize
+    );
+}
+
+#[test]
+fn nbt_unwrap_single() {
+    let mut data = Map::new();
+    data.insert("LevelName".to_string(), Value::from("world"));
+    data.insert("version".to_string(), Value::Int(19133));
+    let mut level = Map::new();
+    level.insert("Data".to_string(), Value::Compound(data.clone()));
+
+    assert_eq!(
+        Value::Compound(level.clone()).unwrap_single(),
+        Value::Compound(data.clone())
+    );
+    // Compounds with several entries, and other values, are unchanged.
+    assert_eq!(
+        Value::Compound(data.clone()).unwrap_single(),
+        Value::Compound(data.clone())
+    );
+    assert_eq!(Value::Int(1).unwrap_single(), Value::Int(1));
+
+    let mut nbt = Blob::new();
+    nbt.insert("Data", Value::Compound(data.clone())).unwrap();
+    assert_eq!(nbt.unwrap_root("Data"), Some(&Value::Compound(data)));
+    assert_eq!(nbt.unwrap_root("Level"), None);
+    nbt.insert("Extra", 1i8).unwrap();
+    assert_eq!(nbt.unwrap_root("Data"), None);
+}
+
+#[test]
+fn nbt_from_dir() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("hematite_nbt_from_dir_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy("tests/small1.nbt", dir.join("a.nbt")).unwrap();
+    fs::copy("tests/level.dat", dir.join("b.dat")).unwrap();
+    fs::write(dir.join("c.dat"), [0x0a, 0x00]).unwrap();
+    fs::write(dir.join("notes.txt"), "not NBT").unwrap();
+
+    let results: Vec<_> = Blob::from_dir(&dir).collect();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let names: Vec<_> = results
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["a.nbt", "b.dat", "c.dat"]);
+
+    let small1 = Blob::from_reader(&mut File::open("tests/small1.nbt").unwrap()).unwrap();
+    assert_eq!(results[0].1, Ok(small1));
+    let level = Blob::from_gzip_reader(&mut File::open("tests/level.dat").unwrap()).unwrap();
+    assert_eq!(results[1].1, Ok(level));
+    assert_eq!(results[2].1, Err(Error::IncompleteRootName));
+
+    let missing: Vec<_> = Blob::from_dir(dir.join("missing")).collect();
+    assert_eq!(missing.len(), 1);
+    assert!(missing[0].1.is_err());
+}
+
+#[test]
+fn nbt_typed_accessors() {
+    assert_eq!(Value::Byte(-1).as_i8(), Some(-1));
+    assert_eq!(Value::Short(300).as_i16(), Some(300));
+    assert_eq!(Value::Int(70000).as_i32(), Some(70000));
+    assert_eq!(Value::Long(1 << 40).as_i64(), Some(1 << 40));
+    assert_eq!(Value::Float(0.5).as_f32(), Some(0.5));
+    assert_eq!(Value::Double(0.25).as_f64(), Some(0.25));
+    assert_eq!(Value::from("stone").as_str(), Some("stone"));
+
+    // There is no conversion between numeric types.
+    assert_eq!(Value::Byte(1).as_i32(), None);
+    assert_eq!(Value::Int(1).as_i64(), None);
+    assert_eq!(Value::Float(1.0).as_f64(), None);
+    assert_eq!(Value::Int(1).as_str(), None);
+}
+
+#[test]
+fn nbt_compact() {
+    let ints = Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+    let mut compound = Map::new();
+    compound.insert("ints".to_string(), ints.clone());
+    compound.insert(
+        "longs".to_string(),
+        Value::List(vec![Value::List(vec![Value::Long(-1)])]),
+    );
+    compound.insert("bytes".to_string(), Value::List(vec![Value::Byte(1)]));
+    compound.insert("shorts".to_string(), Value::List(vec![Value::Short(1)]));
+    compound.insert("empty".to_string(), Value::List(vec![]));
+    let mut nbt = Value::Compound(compound);
+    let before = nbt.len_bytes();
+
+    nbt.compact();
+    let mut expected = Map::new();
+    expected.insert("ints".to_string(), Value::IntArray(vec![1, 2, 3]));
+    expected.insert(
+        "longs".to_string(),
+        Value::List(vec![Value::LongArray(vec![-1])]),
+    );
+    expected.insert("bytes".to_string(), Value::ByteArray(vec![1]));
+    expected.insert("shorts".to_string(), Value::List(vec![Value::Short(1)]));
+    expected.insert("empty".to_string(), Value::List(vec![]));
+    assert_eq!(nbt, Value::Compound(expected));
+
+    // A list of ints has a one-byte element tag that an int array does not.
+    let mut array = ints.clone();
+    array.compact();
+    assert_eq!(array, Value::IntArray(vec![1, 2, 3]));
+    assert_eq!(ints.len_bytes() - array.len_bytes(), 1);
+    assert!(nbt.len_bytes() < before);
+}
+
+#[test]
+fn nbt_compound_navigation() {
+    let mut player = Map::new();
+    player.insert("Pos".to_string(), Value::List(vec![Value::Double(0.5)]));
+    let mut data = Map::new();
+    data.insert("Player".to_string(), Value::Compound(player.clone()));
+    let mut root = Map::new();
+    root.insert("Data".to_string(), Value::Compound(data));
+    let root = Value::Compound(root);
+
+    let found = root.get("Data").and_then(|d| d.get("Player"));
+    assert_eq!(found.and_then(Value::as_compound), Some(&player));
+    let pos = found.and_then(|p| p.get(String::from("Pos")));
+    assert_eq!(
+        pos.and_then(Value::as_list),
+        Some(&[Value::Double(0.5)][..])
+    );
+
+    assert_eq!(root.get("Missing"), None);
+    assert_eq!(Value::Int(1).get("Data"), None);
+    assert_eq!(pos.unwrap().get("0"), None);
+    assert_eq!(Value::Int(1).as_list(), None);
+    assert_eq!(Value::Int(1).as_compound(), None);
+}
+
+#[test]
+fn nbt_stat() {
+    let file = File::open("tests/big1.nbt").unwrap();
+    let options = ReadOptions::default();
+    let stats = Blob::stat(flate2::read::GzDecoder::new(file), &options).unwrap();
+    assert_eq!(
+        stats,
+        BlobStats {
+            tags: 29,
+            max_depth: 3,
+            tag_counts: [0, 1, 1, 1, 8, 3, 1, 1, 5, 2, 6, 0, 0],
+            bytes: 1544,
+        }
+    );
+
+    assert_eq!(
+        Blob::stat(&[0x08, 0x00, 0x00][..], &options),
+        Err(Error::NoRootCompound)
+    );
+    assert_eq!(
+        Blob::stat(&[0x0a, 0x00, 0x00, 0x01][..], &options),
+        Err(Error::IncompleteNbtValue)
+    );
+
+    // The options give the limit on nesting, and the byte order.
+    let bytes = nested_lists(3);
+    let options = ReadOptions {
+        max_depth: 3,
+        ..Default::default()
+    };
+    assert_eq!(
+        Blob::stat(&bytes[..], &options),
+        Err(Error::DepthLimitExceeded)
+    );
+
+    let mut blob = Blob::new();
+    blob.insert("a", 1i32).unwrap();
+    let mut bytes = Vec::new();
+    blob.to_writer_endian(&mut bytes, Endianness::LittleEndian)
+        .unwrap();
+    let options = ReadOptions {
+        endian: Endianness::LittleEndian,
+        ..Default::default()
+    };
+    let stats = Blob::stat(&bytes[..], &options).unwrap();
+    assert_eq!(stats.tags, 2);
+    assert_eq!(stats.bytes, bytes.len() as u64);
+}
+
+#[test]
+fn nbt_path() {
+    let mut player = Map::new();
+    player.insert(
+        "Pos".to_string(),
+        Value::List(vec![Value::Double(0.5), Value::Double(64.0)]),
+    );
+    player.insert("Health".to_string(), Value::Float(20.0));
+    let mut data = Map::new();
+    data.insert("Player".to_string(), Value::Compound(player));
+    let mut nbt = Blob::new();
+    nbt.insert("Data", Value::Compound(data)).unwrap();
+
+    assert_eq!(
+        nbt.path("Data/Player/Pos"),
+        Some(&Value::List(vec![Value::Double(0.5), Value::Double(64.0)]))
+    );
+    assert_eq!(nbt.path("Data/Player/Pos/1"), Some(&Value::Double(64.0)));
+    assert_eq!(
+        nbt.path_with("Data.Player.Health", '.'),
+        Some(&Value::Float(20.0))
+    );
+    assert_eq!(nbt["Data"].path("Player/Health"), Some(&Value::Float(20.0)));
+
+    // Missing keys.
+    assert_eq!(nbt.path("Level"), None);
+    assert_eq!(nbt.path("Data/Player/Motion"), None);
+    // List indices which are out of range or not indices at all.
+    assert_eq!(nbt.path("Data/Player/Pos/2"), None);
+    assert_eq!(nbt.path("Data/Player/Pos/-1"), None);
+    assert_eq!(nbt.path("Data/Player/Pos/x"), None);
+    // Descending through a scalar.
+    assert_eq!(nbt.path("Data/Player/Health/0"), None);
+    assert_eq!(nbt.path("Data/Player/Pos/0/x"), None);
+}
+
+#[test]
+fn nbt_byte_array_as_string() {
+    let text = Value::ByteArray("caf\u{e9} \u{1f600}".bytes().map(|b| b as i8).collect());
+    assert_eq!(
+        text.byte_array_as_string(StringEncoding::Utf8),
+        Some(Ok("caf\u{e9} \u{1f600}".to_string()))
+    );
+    assert_eq!(
+        text.byte_array_as_string(StringEncoding::Cesu8),
+        Some(Ok("caf\u{e9} \u{1f600}".to_string()))
+    );
+
+    // U+1F600 as a CESU-8 surrogate pair, which is not valid UTF-8.
+    let cesu8: Vec<i8> = [0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]
+        .iter()
+        .map(|&b| b as i8)
+        .collect();
+    let cesu8 = Value::ByteArray(cesu8);
+    assert_eq!(
+        cesu8.byte_array_as_string(StringEncoding::Cesu8),
+        Some(Ok("\u{1f600}".to_string()))
+    );
+    assert_eq!(
+        cesu8.byte_array_as_string(StringEncoding::Utf8),
+        Some(Err(Error::InvalidUtf8))
+    );
+
+    let invalid = Value::ByteArray(vec![0x61, -1]);
+    assert_eq!(
+        invalid.byte_array_as_string(StringEncoding::Utf8),
+        Some(Err(Error::InvalidUtf8))
+    );
+    assert_eq!(
+        invalid.byte_array_as_string(StringEncoding::Cesu8),
+        Some(Err(Error::InvalidUtf8))
+    );
+    assert_eq!(
+        Value::String("text".to_string()).byte_array_as_string(StringEncoding::Utf8),
+        None
+    );
+}
+
+#[test]
+fn nbt_from_snbt() {
+    let value = Value::from_snbt(
+        r#"{Health:20b, Pos:[0.0d,64.0d,0.0d], items:[{id:"minecraft:stone",Count:1b}]}"#,
+    )
+    .unwrap();
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::String("minecraft:stone".into()));
+    item.insert("Count".to_string(), Value::Byte(1));
+    let mut expected = Map::new();
+    expected.insert("Health".to_string(), Value::Byte(20));
+    expected.insert(
+        "Pos".to_string(),
+        Value::List(vec![
+            Value::Double(0.0),
+            Value::Double(64.0),
+            Value::Double(0.0),
+        ]),
+    );
+    expected.insert(
+        "items".to_string(),
+        Value::List(vec![Value::Compound(item)]),
+    );
+    assert_eq!(value, Value::Compound(expected));
+
+    // Type suffixes, defaults, and booleans.
+    let list = |s: &str| match Value::from_snbt(s).unwrap() {
+        Value::List(vals) => vals,
+        other => panic!("expected a list, found {:?}", other),
+    };
+    assert_eq!(list("[1s, -2S]"), vec![Value::Short(1), Value::Short(-2)]);
+    assert_eq!(list("[3, +4]"), vec![Value::Int(3), Value::Int(4)]);
+    assert_eq!(list("[5l]"), vec![Value::Long(5)]);
+    assert_eq!(
+        list("[1.5f, 2F]"),
+        vec![Value::Float(1.5), Value::Float(2.0)]
+    );
+    assert_eq!(
+        list("[1.5, .5, 1e3]"),
+        vec![
+            Value::Double(1.5),
+            Value::Double(0.5),
+            Value::Double(1000.0)
+        ]
+    );
+    assert_eq!(list("[true, false]"), vec![Value::Byte(1), Value::Byte(0)]);
+    // Out-of-range numbers are strings, as are other unquoted tokens.
+    assert_eq!(
+        list("[300b, minecraft.stone, 'quoted']"),
+        vec![
+            Value::String("300b".into()),
+            Value::String("minecraft.stone".into()),
+            Value::String("quoted".into()),
+        ]
+    );
+    assert_eq!(
+        list("[-infd, +NaNd, -inf]"),
+        vec![
+            Value::String("-infd".into()),
+            Value::String("+NaNd".into()),
+            Value::String("-inf".into()),
+        ]
+    );
+
+    // Typed arrays.
+    assert_eq!(
+        Value::from_snbt("[B;1b,-2b]"),
+        Ok(Value::ByteArray(vec![1, -2]))
+    );
+    assert_eq!(
+        Value::from_snbt("[I; 1, 2, 3]"),
+        Ok(Value::IntArray(vec![1, 2, 3]))
+    );
+    assert_eq!(
+        Value::from_snbt("[L;1l,2l,]"),
+        Ok(Value::LongArray(vec![1, 2]))
+    );
+    assert_eq!(Value::from_snbt("[I;]"), Ok(Value::IntArray(vec![])));
+
+    // Quoted strings, escapes, whitespace and trailing commas.
+    assert_eq!(
+        Value::from_snbt(r#" { "a key" : 'say "hi"\n' , b : "c\\d" , } "#)
+            .unwrap()
+            .get("a key"),
+        Some(&Value::String("say \"hi\"\n".into()))
+    );
+    assert_eq!(Value::from_snbt("{}"), Ok(Value::Compound(Map::new())));
+    assert_eq!(Value::from_snbt("[]"), Ok(Value::List(vec![])));
+
+    // Malformed input.
+    let err = |s: &str| match Value::from_snbt(s) {
+        Err(Error::SnbtParse(msg)) => msg,
+        other => panic!("expected a parse error for {:?}, found {:?}", s, other),
+    };
+    assert_eq!(
+        err("[1b, 2s]"),
+        "list of TAG_Byte contains a TAG_Short at position 5"
+    );
+    assert_eq!(err("{a:1"), "expected '}' at position 4");
+    assert_eq!(err("{a 1}"), "expected ':' at position 3");
+    assert_eq!(err("{a:}"), "expected a value at position 3");
+    assert_eq!(
+        err("[B;1b,\"x\"]"),
+        "array contains a TAG_String at position 6"
+    );
+    assert_eq!(
+        err("[B;1b,128]"),
+        "value out of range for [B;] at position 6"
+    );
+    assert_eq!(err("{:1}"), "expected a key at position 1");
+    assert_eq!(err("'abc"), "unterminated string at position 0");
+    assert_eq!(err("{a:1} x"), "unexpected 'x' at position 6");
+    assert_eq!(
+        Value::from_snbt(&"[".repeat(1000)),
+        Err(Error::DepthLimitExceeded)
+    );
+}
+
+#[test]
+fn nbt_to_snbt() {
+    assert_eq!(Value::Byte(-1).to_snbt(), "-1b");
+    assert_eq!(Value::Short(2).to_snbt(), "2s");
+    assert_eq!(Value::Int(3).to_snbt(), "3");
+    assert_eq!(Value::Long(4).to_snbt(), "4l");
+    assert_eq!(Value::Float(1.5).to_snbt(), "1.5f");
+    assert_eq!(Value::Double(64.0).to_snbt(), "64.0d");
+    assert_eq!(Value::ByteArray(vec![1, -2]).to_snbt(), "[B;1b,-2b]");
+    assert_eq!(Value::IntArray(vec![1, 2]).to_snbt(), "[I;1,2]");
+    assert_eq!(Value::LongArray(vec![]).to_snbt(), "[L;]");
+    assert_eq!(
+        Value::List(vec![Value::Double(0.5), Value::Double(-1.0)]).to_snbt(),
+        "[0.5d,-1.0d]"
+    );
+
+    // Strings are only quoted when necessary.
+    let string = |s: &str| Value::String(s.to_string()).to_snbt();
+    assert_eq!(string("minecraft.stone_1"), "minecraft.stone_1");
+    assert_eq!(string(""), "\"\"");
+    assert_eq!(string("minecraft:stone"), "\"minecraft:stone\"");
+    assert_eq!(string("say \"hi\" \\o/"), r#""say \"hi\" \\o/""#);
+    assert_eq!(string("12"), "\"12\"");
+    assert_eq!(string("true"), "\"true\"");
+    assert_eq!(string("5€"), "\"5€\"");
+    assert_eq!(string("Größe"), "\"Größe\"");
+
+    // Infinities are written as numbers which overflow back to them.
+    assert_eq!(Value::Float(f32::INFINITY).to_snbt(), "1e999f");
+    assert_eq!(Value::Double(f64::NEG_INFINITY).to_snbt(), "-1e999d");
+
+    let mut nbt = Blob::new();
+    nbt.insert("id", "minecraft:stone").unwrap();
+    assert_eq!(nbt.to_snbt(), r#"{id:"minecraft:stone"}"#);
+
+    // The output reads back as the same value.
+    let mut item = Map::new();
+    item.insert("Count".to_string(), Value::Byte(1));
+    let mut map = Map::new();
+    map.insert(
+        "items".to_string(),
+        Value::List(vec![Value::Compound(item)]),
+    );
+    map.insert("my key".to_string(), Value::LongArray(vec![1, 2]));
+    map.insert("Health".to_string(), Value::Float(20.0));
+    map.insert("name".to_string(), Value::String("12".to_string()));
+    map.insert("price".to_string(), Value::String("5€".to_string()));
+    map.insert("max".to_string(), Value::Double(f64::INFINITY));
+    map.insert("".to_string(), Value::Int(1));
+    let value = Value::Compound(map);
+    assert_eq!(Value::from_snbt(&value.to_snbt()), Ok(value));
+}
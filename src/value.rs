@@ -1,16 +1,19 @@
 use crate::Map;
+use std::cmp;
 use std::fmt;
 use std::io;
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::WriteBytesExt;
+use cesu8::{from_java_cesu8, to_java_cesu8};
 
 use error::{Error, Result};
-use raw;
+use options::{count_nodes, DEFAULT_MAX_DEPTH};
+use raw::{self, Endianness, Format, StringEncoding};
+use snbt;
 
 /// Values which can be represented in the Named Binary Tag format.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
-#[cfg_attr(feature = "serde", derive(Deserialize))]
 #[cfg_attr(feature = "serde", serde(untagged))]
 pub enum Value {
     Byte(i8),
@@ -19,6 +22,7 @@ pub enum Value {
     Long(i64),
     Float(f32),
     Double(f64),
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::i8_array"))]
     ByteArray(Vec<i8>),
     String(String),
     List(Vec<Value>),
@@ -51,68 +55,535 @@ impl Value {
 
     /// A string representation of this tag.
     pub fn tag_name(&self) -> &str {
+        // Every value has a valid type ID.
+        tag_name_for_id(self.id()).unwrap()
+    }
+
+    /// The number of elements in a list, array, or compound, or the number of
+    /// characters in a string. Returns `None` for scalar values.
+    pub fn element_count(&self) -> Option<usize> {
+        match *self {
+            Value::String(ref val) => Some(val.chars().count()),
+            Value::List(ref vals) => Some(vals.len()),
+            Value::Compound(ref vals) => Some(vals.len()),
+            Value::ByteArray(ref vals) => Some(vals.len()),
+            Value::IntArray(ref vals) => Some(vals.len()),
+            Value::LongArray(ref vals) => Some(vals.len()),
+            _ => None,
+        }
+    }
+
+    /// Interprets a `Value::Byte` as a boolean, the way booleans are stored
+    /// in NBT: `0` is `false` and `1` is `true`. Any other byte value, or any
+    /// other kind of value, returns `None`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Byte(0) => Some(false),
+            Value::Byte(1) => Some(true),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Byte`, returns its value.
+    ///
+    /// Like the other numeric accessors, this does not convert between types:
+    /// e.g. `as_i32` returns `None` for a `Value::Byte`.
+    pub fn as_i8(&self) -> Option<i8> {
+        match *self {
+            Value::Byte(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Short`, returns its value.
+    pub fn as_i16(&self) -> Option<i16> {
+        match *self {
+            Value::Short(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Int`, returns its value.
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
+            Value::Int(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Long`, returns its value.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Long(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Float`, returns its value.
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::Float(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Double`, returns its value.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Double(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::String`, returns its contents as a string slice.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::ByteArray`, returns its contents as a slice.
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match *self {
+            Value::ByteArray(ref vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::ByteArray`, returns its contents mutably.
+    pub fn as_byte_array_mut(&mut self) -> Option<&mut Vec<i8>> {
+        match *self {
+            Value::ByteArray(ref mut vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::IntArray`, returns its contents as a slice.
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match *self {
+            Value::IntArray(ref vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::IntArray`, returns its contents mutably.
+    pub fn as_int_array_mut(&mut self) -> Option<&mut Vec<i32>> {
+        match *self {
+            Value::IntArray(ref mut vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::LongArray`, returns its contents as a slice.
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match *self {
+            Value::LongArray(ref vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::LongArray`, returns its contents mutably.
+    ///
+    /// This allows e.g. packed block states to be edited in place.
+    pub fn as_long_array_mut(&mut self) -> Option<&mut Vec<i64>> {
+        match *self {
+            Value::LongArray(ref mut vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::List`, returns its elements as a slice.
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match *self {
+            Value::List(ref vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Compound`, returns its entries.
+    pub fn as_compound(&self) -> Option<&Map<String, Value>> {
+        match *self {
+            Value::Compound(ref vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Compound`, returns the value of its entry named
+    /// `key`, if there is one. Returns `None` for any other kind of value, so
+    /// that nested compounds can be walked with e.g.
+    /// `nbt.get("Data")?.get("Player")`.
+    pub fn get<S>(&self, key: S) -> Option<&Value>
+    where
+        S: AsRef<str>,
+    {
+        self.as_compound()?.get(key.as_ref())
+    }
+
+    /// Walks a `/`-separated path through nested compounds and lists, e.g.
+    /// `"Player/Inventory/0/id"`, where numeric segments index into lists.
+    ///
+    /// Returns `None` at the first segment which names a missing entry, an
+    /// out-of-range index, or descends into a value which is neither a
+    /// compound nor a list.
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        self.path_with(path, '/')
+    }
+
+    /// Walks a path through nested compounds and lists as `path` does, but
+    /// with segments separated by `sep` instead of `/`.
+    pub fn path_with(&self, path: &str, sep: char) -> Option<&Value> {
+        path.split(sep).try_fold(self, Value::child)
+    }
+
+    /// Returns the entry named `segment` of a compound, or the element of a
+    /// list at the index it spells out.
+    fn child(&self, segment: &str) -> Option<&Value> {
+        match *self {
+            Value::Compound(ref map) => map.get(segment),
+            Value::List(ref vals) => vals.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    /// Returns the child named by `segment` mutably, as `child` does.
+    pub(crate) fn child_mut(&mut self, segment: &str) -> Option<&mut Value> {
+        match *self {
+            Value::Compound(ref mut map) => map.get_mut(segment),
+            Value::List(ref mut vals) => vals.get_mut(segment.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::List`, returns its elements mutably.
+    ///
+    /// Note that every element of a list must have the same tag type for it to
+    /// be written, which `Value::validate` can check after editing.
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::List(ref mut vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Value::Compound`, returns its entries mutably.
+    pub fn as_compound_mut(&mut self) -> Option<&mut Map<String, Value>> {
         match *self {
-            Value::Byte(_) => "TAG_Byte",
-            Value::Short(_) => "TAG_Short",
-            Value::Int(_) => "TAG_Int",
-            Value::Long(_) => "TAG_Long",
-            Value::Float(_) => "TAG_Float",
-            Value::Double(_) => "TAG_Double",
-            Value::ByteArray(_) => "TAG_ByteArray",
-            Value::String(_) => "TAG_String",
-            Value::List(_) => "TAG_List",
-            Value::Compound(_) => "TAG_Compound",
-            Value::IntArray(_) => "TAG_IntArray",
-            Value::LongArray(_) => "TAG_LongArray",
+            Value::Compound(ref mut vals) => Some(vals),
+            _ => None,
         }
     }
 
+    /// If this is a `Value::ByteArray`, returns an iterator over its elements,
+    /// without copying them into a new `Vec`.
+    pub fn byte_array_iter(&self) -> Option<impl Iterator<Item = i8> + '_> {
+        self.as_byte_array().map(|vals| vals.iter().cloned())
+    }
+
+    /// If this is a `Value::IntArray`, returns an iterator over its elements,
+    /// without copying them into a new `Vec`.
+    pub fn int_array_iter(&self) -> Option<impl Iterator<Item = i32> + '_> {
+        self.as_int_array().map(|vals| vals.iter().cloned())
+    }
+
+    /// If this is a `Value::LongArray`, returns an iterator over its elements,
+    /// without copying them into a new `Vec`.
+    pub fn long_array_iter(&self) -> Option<impl Iterator<Item = i64> + '_> {
+        self.as_long_array().map(|vals| vals.iter().cloned())
+    }
+
+    /// If this is a `Value::IntArray` or `Value::LongArray`, returns its
+    /// contents as big-endian bytes, as they are stored in NBT data.
+    pub fn to_be_bytes(&self) -> Option<Vec<u8>> {
+        match *self {
+            Value::IntArray(ref vals) => Some(vals.iter().flat_map(|v| v.to_be_bytes()).collect()),
+            Value::LongArray(ref vals) => Some(vals.iter().flat_map(|v| v.to_be_bytes()).collect()),
+            _ => None,
+        }
+    }
+
+    /// Creates a `Value::IntArray` from big-endian bytes. This is the inverse
+    /// of `to_be_bytes`.
+    ///
+    /// Returns `Error::IncompleteNbtValue` if the number of bytes is not a
+    /// multiple of 4.
+    pub fn int_array_from_be_bytes(bytes: &[u8]) -> Result<Value> {
+        let chunks = bytes.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::IncompleteNbtValue);
+        }
+        Ok(Value::IntArray(
+            chunks
+                .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        ))
+    }
+
+    /// Creates a `Value::LongArray` from big-endian bytes. This is the inverse
+    /// of `to_be_bytes`.
+    ///
+    /// Returns `Error::IncompleteNbtValue` if the number of bytes is not a
+    /// multiple of 8.
+    pub fn long_array_from_be_bytes(bytes: &[u8]) -> Result<Value> {
+        let chunks = bytes.chunks_exact(8);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::IncompleteNbtValue);
+        }
+        Ok(Value::LongArray(
+            chunks
+                .map(|b| {
+                    let mut word = [0; 8];
+                    word.copy_from_slice(b);
+                    i64::from_be_bytes(word)
+                })
+                .collect(),
+        ))
+    }
+
+    /// If this is a `Value::String`, returns its contents encoded as Java's
+    /// modified UTF-8 (CESU-8), as they are stored in NBT data, without the
+    /// length prefix.
+    pub fn string_to_cesu8_bytes(&self) -> Option<Vec<u8>> {
+        match *self {
+            Value::String(ref val) => Some(to_java_cesu8(val).into_owned()),
+            _ => None,
+        }
+    }
+
+    /// Creates a `Value::String` from bytes encoded as Java's modified UTF-8
+    /// (CESU-8). This is the inverse of `string_to_cesu8_bytes`.
+    ///
+    /// Returns `Error::InvalidUtf8` if the bytes are not valid CESU-8.
+    pub fn string_from_cesu8(bytes: &[u8]) -> Result<Value> {
+        Ok(Value::String(from_java_cesu8(bytes)?.into_owned()))
+    }
+
+    /// If this is a `Value::ByteArray`, interprets its bytes as text in the
+    /// given `encoding`, as some mods use byte arrays to store strings.
+    ///
+    /// The inner result is `Error::InvalidUtf8` if the bytes are not valid in
+    /// that encoding.
+    pub fn byte_array_as_string(&self, encoding: StringEncoding) -> Option<Result<String>> {
+        let bytes: Vec<u8> = self.byte_array_iter()?.map(|b| b as u8).collect();
+        Some(match encoding {
+            StringEncoding::Utf8 => String::from_utf8(bytes).map_err(|_| Error::InvalidUtf8),
+            StringEncoding::Cesu8 => from_java_cesu8(&bytes)
+                .map(|s| s.into_owned())
+                .map_err(From::from),
+        })
+    }
+
+    /// Parses a value from stringified NBT (SNBT), the text format used by
+    /// Minecraft commands, e.g. `{Health:20b, Pos:[0.0d,64.0d,0.0d]}`.
+    ///
+    /// Numbers take their type from a suffix (`b`, `s`, `l`, `f` or `d`),
+    /// and are `Int`s or `Double`s without one; `true` and `false` are bytes.
+    /// Typed arrays are written `[B;1b,2b]`, `[I;1,2]` and `[L;1l,2l]`.
+    /// Strings may be quoted with `"` or `'`, and need not be if they only
+    /// contain the characters `[A-Za-z0-9_.+-]`.
+    ///
+    /// Returns `Error::SnbtParse` if the text is malformed, including if a
+    /// list holds values of different types.
+    ///
+    /// ```rust
+    /// use nbt::Value;
+    ///
+    /// let value = Value::from_snbt("{Health:20b, Pos:[0.0d,64.0d,0.0d]}").unwrap();
+    /// assert_eq!(value.get("Health"), Some(&Value::Byte(20)));
+    /// ```
+    pub fn from_snbt(src: &str) -> Result<Value> {
+        snbt::parse(src)
+    }
+
+    /// Writes this value as stringified NBT (SNBT), in the compact form used
+    /// by Minecraft's `/data` command, e.g. `{Health:20b,Pos:[0.0d,64.0d]}`.
+    ///
+    /// Numbers carry their type suffix (none for `Int`s), and arrays are
+    /// written as `[B;...]`, `[I;...]` and `[L;...]`. Strings and keys are
+    /// left unquoted if they only contain the characters `[A-Za-z0-9_.+-]`,
+    /// except for strings which would otherwise read back as numbers or
+    /// booleans. Unlike the `Display` implementation, the result can be read
+    /// back with `from_snbt`, except for NaN floats, which SNBT has no way
+    /// to write and which read back as strings. Infinite floats are written as
+    /// `1e999f` or `1e999d`, which read back as infinity.
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        snbt::write_value(&mut out, self);
+        out
+    }
+
+    /// Converts this value into a list: a `Value::List` is returned unchanged,
+    /// and any other value is wrapped in a list of one element.
+    ///
+    /// This is useful for fields that Minecraft stores as either a single
+    /// value or a list of values.
+    pub fn into_list(self) -> Value {
+        match self {
+            Value::List(_) => self,
+            other => Value::List(vec![other]),
+        }
+    }
+
+    /// If this is a `Value::Compound` with exactly one entry, returns the value
+    /// of that entry, and otherwise returns this value unchanged.
+    ///
+    /// This unwraps data which is nested one level deep for no other reason,
+    /// such as the `Data` compound of `level.dat`.
+    pub fn unwrap_single(self) -> Value {
+        match self {
+            Value::Compound(map) => {
+                if map.len() == 1 {
+                    map.into_iter().next().unwrap().1
+                } else {
+                    Value::Compound(map)
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// If this is a `Value::List`, splits its elements into those for which
+    /// `pred` returns `true` and those for which it returns `false`, keeping
+    /// their order. Returns `None` for any other kind of value.
+    pub fn partition_list_by<F>(&self, pred: F) -> Option<(Vec<&Value>, Vec<&Value>)>
+    where
+        F: Fn(&Value) -> bool,
+    {
+        match *self {
+            Value::List(ref vals) => Some(vals.iter().partition(|v| pred(v))),
+            _ => None,
+        }
+    }
+
+    /// Adds `delta` to an integer value in place, keeping its tag type.
+    ///
+    /// Returns `Error::Overflow` (leaving the value unchanged) if the result
+    /// does not fit in the range of the value's tag type, and
+    /// `Error::TagMismatch` if this is not an integer value.
+    pub fn add_i64(&mut self, delta: i64) -> Result<()> {
+        use std::convert::TryFrom;
+
+        match *self {
+            Value::Byte(ref mut v) => {
+                *v = i64::from(*v)
+                    .checked_add(delta)
+                    .and_then(|n| i8::try_from(n).ok())
+                    .ok_or(Error::Overflow)?
+            }
+            Value::Short(ref mut v) => {
+                *v = i64::from(*v)
+                    .checked_add(delta)
+                    .and_then(|n| i16::try_from(n).ok())
+                    .ok_or(Error::Overflow)?
+            }
+            Value::Int(ref mut v) => {
+                *v = i64::from(*v)
+                    .checked_add(delta)
+                    .and_then(|n| i32::try_from(n).ok())
+                    .ok_or(Error::Overflow)?
+            }
+            Value::Long(ref mut v) => *v = v.checked_add(delta).ok_or(Error::Overflow)?,
+            _ => return Err(Error::TagMismatch(self.id(), 0x04)),
+        }
+        Ok(())
+    }
+
     /// Writes the payload of this `Value` to an `io::Write` destination.
-    pub fn to_writer<W>(&self, mut dst: &mut W) -> Result<()>
+    pub fn to_writer<W>(&self, dst: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.write_payload(dst, Endianness::BigEndian.into())
+    }
+
+    /// Writes this `Value` as a complete named element, i.e. its tag and
+    /// `name` followed by its payload, to an `io::Write` destination. This is
+    /// the inverse of `read_named`, and any kind of value can be written.
+    pub fn to_named_writer<W>(&self, dst: &mut W, name: &str, endian: Endianness) -> Result<()>
     where
-        W: io::Write,
+        W: ?Sized + io::Write,
+    {
+        raw::write_header(dst, self.id(), name, endian)?;
+        self.write_payload(dst, endian.into())
+    }
+
+    /// Returns the payload of this `Value`, without a tag or name, as bytes.
+    ///
+    /// For a `Value::Compound` this is just its entries followed by the
+    /// closing `TAG_End`, i.e. what a `Blob` writes after its root header.
+    pub fn to_payload_vec(&self, endian: Endianness) -> Result<Vec<u8>> {
+        let mut dst = Vec::with_capacity(self.len_payload());
+        self.write_payload(&mut dst, endian.into())?;
+        Ok(dst)
+    }
+
+    pub(crate) fn write_payload<W>(&self, dst: &mut W, format: Format) -> Result<()>
+    where
+        W: ?Sized + io::Write,
     {
         match *self {
             Value::Byte(val) => raw::write_bare_byte(dst, val),
-            Value::Short(val) => raw::write_bare_short(dst, val),
-            Value::Int(val) => raw::write_bare_int(dst, val),
-            Value::Long(val) => raw::write_bare_long(dst, val),
-            Value::Float(val) => raw::write_bare_float(dst, val),
-            Value::Double(val) => raw::write_bare_double(dst, val),
-            Value::ByteArray(ref vals) => raw::write_bare_byte_array(dst, &vals[..]),
-            Value::String(ref val) => raw::write_bare_string(dst, &val),
+            Value::Short(val) => raw::write_bare_short(dst, val, format),
+            Value::Int(val) => raw::write_bare_int(dst, val, format),
+            Value::Long(val) => raw::write_bare_long(dst, val, format),
+            Value::Float(val) => raw::write_bare_float(dst, val, format),
+            Value::Double(val) => raw::write_bare_double(dst, val, format),
+            Value::ByteArray(ref vals) => raw::write_bare_byte_array(dst, &vals[..], format),
+            Value::String(ref val) => raw::write_bare_string(dst, &val, format),
             Value::List(ref vals) => {
                 // This is a bit of a trick: if the list is empty, don't bother
                 // checking its type.
                 if vals.is_empty() {
                     dst.write_u8(0)?; // TAG_End
-                    dst.write_i32::<BigEndian>(0)?;
+                    raw::write_bare_int(dst, 0, format)?;
                 } else {
                     // Otherwise, use the first element of the list.
                     let first_id = vals[0].id();
                     dst.write_u8(first_id)?;
-                    dst.write_i32::<BigEndian>(vals.len() as i32)?;
+                    raw::write_bare_int(dst, vals.len() as i32, format)?;
                     for nbt in vals {
                         // Ensure that all of the tags are the same type.
                         if nbt.id() != first_id {
                             return Err(Error::HeterogeneousList);
                         }
-                        nbt.to_writer(dst)?;
+                        nbt.write_payload(dst, format)?;
                     }
                 }
                 Ok(())
             }
             Value::Compound(ref vals) => {
                 for (name, ref nbt) in vals {
-                    // Write the header for the tag.
-                    dst.write_u8(nbt.id())?;
-                    raw::write_bare_string(dst, name)?;
-                    nbt.to_writer(dst)?;
+                    raw::write_header(dst, nbt.id(), name, format)?;
+                    nbt.write_payload(dst, format)?;
+                }
+                raw::close_nbt(dst)
+            }
+            Value::IntArray(ref vals) => raw::write_bare_int_array(dst, &vals[..], format),
+            Value::LongArray(ref vals) => raw::write_bare_long_array(dst, &vals[..], format),
+        }
+    }
+
+    /// Writes the payload of this `Value` in a canonical form, in which the
+    /// entries of every compound are sorted by name, so that equal values
+    /// always produce the same bytes regardless of map ordering.
+    ///
+    /// Unlike `write_payload`, lists are not checked for homogeneity.
+    pub(crate) fn write_canonical_payload<W>(&self, dst: &mut W) -> Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let endian = Endianness::BigEndian;
+        match *self {
+            Value::List(ref vals) => {
+                dst.write_u8(vals.first().map_or(0, Value::id))?;
+                raw::write_bare_int(dst, vals.len() as i32, endian)?;
+                for nbt in vals {
+                    nbt.write_canonical_payload(dst)?;
                 }
-                raw::close_nbt(&mut dst)
+                Ok(())
             }
-            Value::IntArray(ref vals) => raw::write_bare_int_array(dst, &vals[..]),
-            Value::LongArray(ref vals) => raw::write_bare_long_array(dst, &vals[..]),
+            Value::Compound(ref vals) => write_canonical_compound(dst, vals),
+            _ => self.write_payload(dst, endian.into()),
         }
     }
 
@@ -120,46 +591,379 @@ impl Value {
     /// `io::Read` source.
     pub fn from_reader<R>(id: u8, src: &mut R) -> Result<Value>
     where
-        R: io::Read,
+        R: ?Sized + io::Read,
+    {
+        Value::read_payload(id, src, Endianness::BigEndian.into())
+    }
+
+    /// Reads a complete named value, such as the root of an NBT file, from an
+    /// `io::Read` source, returning its name along with the value.
+    ///
+    /// Unlike `Blob::from_reader`, this does not require the root to be a
+    /// compound.
+    pub fn read_named<R>(src: &mut R, endian: Endianness) -> Result<(String, Value)>
+    where
+        R: ?Sized + io::Read,
+    {
+        let (id, name) = raw::emit_next_header(src, endian)?;
+        let value = Value::read_payload(id, src, endian.into())?;
+        Ok((name, value))
+    }
+
+    pub(crate) fn read_payload<R>(id: u8, src: &mut R, format: Format) -> Result<Value>
+    where
+        R: ?Sized + io::Read,
     {
+        let mut nodes = usize::MAX;
+        Value::read_payload_limited(id, src, format, DEFAULT_MAX_DEPTH, &mut nodes)
+    }
+
+    /// Reads a payload, allowing at most `depth` levels of compounds and lists
+    /// (including this one) to be nested, and at most `nodes` more tags to be
+    /// read inside it. `nodes` is reduced by the number of tags read.
+    pub(crate) fn read_payload_limited<R>(
+        id: u8,
+        src: &mut R,
+        format: Format,
+        depth: usize,
+        nodes: &mut usize,
+    ) -> Result<Value>
+    where
+        R: ?Sized + io::Read,
+    {
+        if (id == 0x09 || id == 0x0a) && depth == 0 {
+            return Err(Error::DepthLimitExceeded);
+        }
+        // Containers are read here, and everything else in a separate
+        // function, to keep the stack frames of deeply nested data small.
         match id {
-            0x01 => Ok(Value::Byte(raw::read_bare_byte(src)?)),
-            0x02 => Ok(Value::Short(raw::read_bare_short(src)?)),
-            0x03 => Ok(Value::Int(raw::read_bare_int(src)?)),
-            0x04 => Ok(Value::Long(raw::read_bare_long(src)?)),
-            0x05 => Ok(Value::Float(raw::read_bare_float(src)?)),
-            0x06 => Ok(Value::Double(raw::read_bare_double(src)?)),
-            0x07 => Ok(Value::ByteArray(raw::read_bare_byte_array(src)?)),
-            0x08 => Ok(Value::String(raw::read_bare_string(src)?)),
             0x09 => {
                 // List
-                let id = src.read_u8()?;
-                let len = src.read_i32::<BigEndian>()? as usize;
+                let (id, len) = raw::read_list_header(src, format)?;
+                let len = len.max(0) as usize;
+                count_nodes(nodes, len)?;
                 let mut buf = Vec::with_capacity(len);
                 for _ in 0..len {
-                    buf.push(Value::from_reader(id, src)?);
+                    buf.push(Value::read_payload_limited(
+                        id,
+                        src,
+                        format,
+                        depth - 1,
+                        nodes,
+                    )?);
                 }
                 Ok(Value::List(buf))
             }
             0x0a => {
                 // Compound
                 let mut buf = Map::new();
-                loop {
-                    let (id, name) = raw::emit_next_header(src)?;
-                    if id == 0x00 {
-                        break;
-                    }
-                    let tag = Value::from_reader(id, src)?;
-                    buf.insert(name, tag);
-                }
+                read_compound_into(&mut buf, src, format, depth - 1, nodes, false)?;
                 Ok(Value::Compound(buf))
             }
-            0x0b => Ok(Value::IntArray(raw::read_bare_int_array(src)?)),
-            0x0c => Ok(Value::LongArray(raw::read_bare_long_array(src)?)),
+            _ => Value::read_flat_payload(id, src, format),
+        }
+    }
+
+    /// Reads the payload of any value other than a list or compound.
+    fn read_flat_payload<R>(id: u8, src: &mut R, format: Format) -> Result<Value>
+    where
+        R: ?Sized + io::Read,
+    {
+        match id {
+            0x01 => Ok(Value::Byte(raw::read_bare_byte(src)?)),
+            0x02 => Ok(Value::Short(raw::read_bare_short(src, format)?)),
+            0x03 => Ok(Value::Int(raw::read_bare_int(src, format)?)),
+            0x04 => Ok(Value::Long(raw::read_bare_long(src, format)?)),
+            0x05 => Ok(Value::Float(raw::read_bare_float(src, format)?)),
+            0x06 => Ok(Value::Double(raw::read_bare_double(src, format)?)),
+            0x07 => Ok(Value::ByteArray(raw::read_bare_byte_array(src, format)?)),
+            0x08 => Ok(Value::String(raw::read_bare_string(src, format)?)),
+            0x0b => Ok(Value::IntArray(raw::read_bare_int_array(src, format)?)),
+            0x0c => Ok(Value::LongArray(raw::read_bare_long_array(src, format)?)),
             e => Err(Error::InvalidTypeId(e)),
         }
     }
 
+    /// Checks that this value can be written in the NBT format: that every
+    /// list (including nested ones) is homogeneous, and that no string, list,
+    /// array, or compound key is too long for its length to be encoded.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Value::String(ref val) => validate_string(val),
+            Value::ByteArray(ref vals) => validate_len(vals.len(), "TAG_ByteArray"),
+            Value::IntArray(ref vals) => validate_len(vals.len(), "TAG_IntArray"),
+            Value::LongArray(ref vals) => validate_len(vals.len(), "TAG_LongArray"),
+            Value::List(ref vals) => {
+                validate_len(vals.len(), "TAG_List")?;
+                if let Some(first) = vals.first() {
+                    if vals.iter().any(|nbt| nbt.id() != first.id()) {
+                        return Err(Error::HeterogeneousList);
+                    }
+                }
+                vals.iter().try_for_each(Value::validate)
+            }
+            Value::Compound(ref vals) => vals.iter().try_for_each(|(name, nbt)| {
+                validate_string(name)?;
+                nbt.validate()
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Recursively sorts the keys of every `Value::Compound` in this value,
+    /// so that it serializes to the same bytes regardless of the order in
+    /// which entries were inserted.
+    ///
+    /// This is only meaningful when the `preserve_order` feature is enabled;
+    /// otherwise compounds have no defined order and are left untouched.
+    pub fn sort_compounds(&mut self) {
+        match *self {
+            Value::Compound(ref mut vals) => {
+                #[cfg(feature = "preserve_order")]
+                vals.sort_keys();
+                for nbt in vals.values_mut() {
+                    nbt.sort_compounds();
+                }
+            }
+            Value::List(ref mut vals) => {
+                for nbt in vals {
+                    nbt.sort_compounds();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Recursively converts every non-empty `Value::List` of bytes, ints or
+    /// longs in this value into the matching array. Arrays are written without
+    /// the element tag byte that lists have, and are read more cheaply.
+    ///
+    /// Note that Minecraft does not treat lists and arrays interchangeably, so
+    /// this should only be used for data whose reader accepts either.
+    pub fn compact(&mut self) {
+        let array = match *self {
+            Value::Compound(ref mut vals) => {
+                for nbt in vals.values_mut() {
+                    nbt.compact();
+                }
+                return;
+            }
+            Value::List(ref mut vals) => match vals.first().map(Value::id) {
+                Some(0x01) if vals.iter().all(|v| v.id() == 0x01) => {
+                    Value::ByteArray(vals.iter().filter_map(Value::as_i8).collect())
+                }
+                Some(0x03) if vals.iter().all(|v| v.id() == 0x03) => {
+                    Value::IntArray(vals.iter().filter_map(Value::as_i32).collect())
+                }
+                Some(0x04) if vals.iter().all(|v| v.id() == 0x04) => {
+                    Value::LongArray(vals.iter().filter_map(Value::as_i64).collect())
+                }
+                _ => {
+                    for nbt in vals {
+                        nbt.compact();
+                    }
+                    return;
+                }
+            },
+            _ => return,
+        };
+        *self = array;
+    }
+
+    /// Recursively rewrites every `Value::String` in this value, including
+    /// those nested inside lists and compounds, using `f`. Other values are
+    /// left untouched.
+    pub fn map_strings<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        self.map_strings_dyn(&mut f)
+    }
+
+    fn map_strings_dyn(&mut self, f: &mut dyn FnMut(&str) -> String) {
+        match *self {
+            Value::String(ref mut val) => *val = f(val),
+            Value::List(ref mut vals) => {
+                for nbt in vals {
+                    nbt.map_strings_dyn(f);
+                }
+            }
+            Value::Compound(ref mut vals) => {
+                for nbt in vals.values_mut() {
+                    nbt.map_strings_dyn(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Retains only the compound entries for which `f(name, value)` returns
+    /// `true`, at every depth of this value, including inside lists. Entries
+    /// are checked before their contents, so the contents of a removed entry
+    /// are never visited.
+    pub fn retain_recursive<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &Value) -> bool,
+    {
+        self.retain_recursive_dyn(&mut f)
+    }
+
+    fn retain_recursive_dyn(&mut self, f: &mut dyn FnMut(&str, &Value) -> bool) {
+        match *self {
+            Value::List(ref mut vals) => {
+                for nbt in vals {
+                    nbt.retain_recursive_dyn(f);
+                }
+            }
+            Value::Compound(ref mut vals) => {
+                vals.retain(|name, nbt| f(name, nbt));
+                for nbt in vals.values_mut() {
+                    nbt.retain_recursive_dyn(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Calls `f(path, value)` for this value and every value nested inside
+    /// it, parents before their contents. Paths are made of compound keys and
+    /// list indices separated by dots, e.g. `"Inventory.0.id"`, and this value
+    /// itself has the empty path.
+    ///
+    /// Unless the `preserve_order` feature is enabled, the order in which the
+    /// entries of a compound are visited is unspecified.
+    pub fn walk<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(&str, &'a Value),
+    {
+        self.walk_dyn(&mut String::new(), &mut f)
+    }
+
+    fn walk_dyn<'a>(&'a self, path: &mut String, f: &mut dyn FnMut(&str, &'a Value)) {
+        f(path, self);
+        match *self {
+            Value::List(ref vals) => {
+                for (i, nbt) in vals.iter().enumerate() {
+                    nbt.walk_child(path, &i.to_string(), f);
+                }
+            }
+            Value::Compound(ref vals) => {
+                for (name, nbt) in vals {
+                    nbt.walk_child(path, name, f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Walks this value as the child `name` of the value at `path`.
+    pub(crate) fn walk_child<'a>(
+        &'a self,
+        path: &mut String,
+        name: &str,
+        f: &mut dyn FnMut(&str, &'a Value),
+    ) {
+        let len = path.len();
+        if len > 0 {
+            path.push('.');
+        }
+        path.push_str(name);
+        self.walk_dyn(path, f);
+        path.truncate(len);
+    }
+
+    /// Finds every string nested in this value (see `walk`) for which
+    /// `pred` returns `true`, along with its path.
+    pub fn find_strings<F>(&self, pred: F) -> Vec<(String, &str)>
+    where
+        F: Fn(&str) -> bool,
+    {
+        find_strings_with(|f| self.walk(f), pred)
+    }
+
+    /// Gathers every numeric value in this value, including the contents of
+    /// arrays, lists, and compounds, in traversal order. Integers are widened
+    /// to `f64`, which is exact for all but the largest `Long` values.
+    ///
+    /// Unless the `preserve_order` feature is enabled, the order in which the
+    /// entries of a compound are visited is unspecified.
+    pub fn collect_scalars(&self) -> Vec<f64> {
+        let mut out = Vec::new();
+        self.push_scalars(&mut out);
+        out
+    }
+
+    fn push_scalars(&self, out: &mut Vec<f64>) {
+        match *self {
+            Value::Byte(v) => out.push(f64::from(v)),
+            Value::Short(v) => out.push(f64::from(v)),
+            Value::Int(v) => out.push(f64::from(v)),
+            Value::Long(v) => out.push(v as f64),
+            Value::Float(v) => out.push(f64::from(v)),
+            Value::Double(v) => out.push(v),
+            Value::String(_) => {}
+            Value::ByteArray(ref vals) => out.extend(vals.iter().map(|&v| f64::from(v))),
+            Value::IntArray(ref vals) => out.extend(vals.iter().map(|&v| f64::from(v))),
+            Value::LongArray(ref vals) => out.extend(vals.iter().map(|&v| v as f64)),
+            Value::List(ref vals) => {
+                for nbt in vals {
+                    nbt.push_scalars(out);
+                }
+            }
+            Value::Compound(ref vals) => {
+                for nbt in vals.values() {
+                    nbt.push_scalars(out);
+                }
+            }
+        }
+    }
+
+    /// Converts this value to JSON.
+    ///
+    /// If `tag_arrays` is `false`, lists and arrays are both emitted as plain
+    /// JSON arrays, which loses the distinction between them. Otherwise each
+    /// is wrapped in a single-entry object keyed by its kind, e.g.
+    /// `{"int_array": [1, 2]}` or `{"list": [1, 2]}`, so that the original
+    /// tag types can be recovered. Compounds are always emitted as JSON
+    /// objects.
+    ///
+    /// Non-finite floats have no JSON representation and become `null`.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self, tag_arrays: bool) -> serde_json::Value {
+        use serde_json::Value as Json;
+
+        let (kind, vals) = match *self {
+            Value::Byte(v) => return Json::from(v),
+            Value::Short(v) => return Json::from(v),
+            Value::Int(v) => return Json::from(v),
+            Value::Long(v) => return Json::from(v),
+            Value::Float(v) => return Json::from(v),
+            Value::Double(v) => return Json::from(v),
+            Value::String(ref v) => return Json::from(v.as_str()),
+            Value::Compound(ref vals) => {
+                return Json::Object(
+                    vals.iter()
+                        .map(|(name, nbt)| (name.clone(), nbt.to_json(tag_arrays)))
+                        .collect(),
+                )
+            }
+            Value::List(ref vals) => (
+                "list",
+                vals.iter().map(|nbt| nbt.to_json(tag_arrays)).collect(),
+            ),
+            Value::ByteArray(ref vals) => ("byte_array", Json::from(vals.clone())),
+            Value::IntArray(ref vals) => ("int_array", Json::from(vals.clone())),
+            Value::LongArray(ref vals) => ("long_array", Json::from(vals.clone())),
+        };
+        if tag_arrays {
+            let mut tagged = serde_json::Map::new();
+            tagged.insert(kind.to_string(), vals);
+            Json::Object(tagged)
+        } else {
+            vals
+        }
+    }
+
     pub fn print(&self, f: &mut fmt::Formatter, offset: usize) -> fmt::Result {
         match *self {
             Value::Byte(v) => write!(f, "{}", v),
@@ -260,6 +1064,136 @@ impl Value {
     }
 }
 
+/// A string representation of the tag with the given type ID, such as
+/// `"TAG_Int"` for `0x03`, or `None` if the ID is not a valid tag.
+///
+/// This includes `"TAG_End"` for `0x00`, which marks the end of a compound
+/// (or the element type of an empty list) but has no `Value`.
+pub fn tag_name_for_id(id: u8) -> Option<&'static str> {
+    match id {
+        0x00 => Some("TAG_End"),
+        0x01 => Some("TAG_Byte"),
+        0x02 => Some("TAG_Short"),
+        0x03 => Some("TAG_Int"),
+        0x04 => Some("TAG_Long"),
+        0x05 => Some("TAG_Float"),
+        0x06 => Some("TAG_Double"),
+        0x07 => Some("TAG_ByteArray"),
+        0x08 => Some("TAG_String"),
+        0x09 => Some("TAG_List"),
+        0x0a => Some("TAG_Compound"),
+        0x0b => Some("TAG_IntArray"),
+        0x0c => Some("TAG_LongArray"),
+        _ => None,
+    }
+}
+
+/// Collects every string visited by `walk` for which `pred` returns `true`,
+/// along with its path. Shared by `Value::find_strings` and
+/// `Blob::find_strings`.
+pub(crate) fn find_strings_with<'a, W, F>(walk: W, pred: F) -> Vec<(String, &'a str)>
+where
+    W: FnOnce(&mut dyn FnMut(&str, &'a Value)),
+    F: Fn(&str) -> bool,
+{
+    let mut found = Vec::new();
+    walk(&mut |path, nbt| {
+        if let Value::String(ref s) = *nbt {
+            if pred(s) {
+                found.push((path.to_string(), &s[..]));
+            }
+        }
+    });
+    found
+}
+
+/// Writes the entries of a compound sorted by name, followed by `TAG_End`.
+pub(crate) fn write_canonical_compound<W>(dst: &mut W, vals: &Map<String, Value>) -> Result<()>
+where
+    W: ?Sized + io::Write,
+{
+    let mut entries: Vec<_> = vals.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, nbt) in entries {
+        raw::write_header(dst, nbt.id(), name, Endianness::BigEndian)?;
+        nbt.write_canonical_payload(dst)?;
+    }
+    raw::close_nbt(dst)
+}
+
+/// Reads the entries of a compound payload into `buf`, with the same limits
+/// as `Value::read_payload_limited`, where `depth` applies to each entry. If
+/// `missing_end_ok` is set, the data may end where the closing `TAG_End`
+/// would be, as if it were there.
+pub(crate) fn read_compound_into<R>(
+    buf: &mut Map<String, Value>,
+    src: &mut R,
+    format: Format,
+    depth: usize,
+    nodes: &mut usize,
+    missing_end_ok: bool,
+) -> Result<()>
+where
+    R: ?Sized + io::Read,
+{
+    loop {
+        let id = match raw::read_bare_byte(src) {
+            Err(Error::IncompleteNbtValue) if missing_end_ok => return Ok(()),
+            id => id? as u8,
+        };
+        if id == 0x00 {
+            return Ok(());
+        }
+        let name = raw::read_bare_string(src, format)?;
+        count_nodes(nodes, 1)?;
+        let tag = Value::read_payload_limited(id, src, format, depth, nodes)?;
+        buf.insert(name, tag);
+    }
+}
+
+/// Merges the entries of `src` into `dst`, recursing into compounds present
+/// in both. Other values in `src` replace those in `dst`, but only if they have
+/// the same tag type; otherwise `Error::TagMismatch` is returned, with the tag
+/// from `src` first. `dst` may be partially merged when this fails.
+pub(crate) fn merge_compound_strict(
+    dst: &mut Map<String, Value>,
+    src: &Map<String, Value>,
+) -> Result<()> {
+    for (name, nbt) in src {
+        match (dst.get_mut(name), nbt) {
+            (Some(&mut Value::Compound(ref mut inner)), Value::Compound(other)) => {
+                merge_compound_strict(inner, other)?;
+            }
+            (Some(existing), _) => {
+                if existing.id() != nbt.id() {
+                    return Err(Error::TagMismatch(nbt.id(), existing.id()));
+                }
+                *existing = nbt.clone();
+            }
+            (None, _) => {
+                dst.insert(name.clone(), nbt.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Strings are prefixed with their (modified UTF-8) length as a `u16`.
+pub(crate) fn validate_string(val: &str) -> Result<()> {
+    if to_java_cesu8(val).len() > usize::from(u16::MAX) {
+        return Err(Error::LengthOverflow("TAG_String"));
+    }
+    Ok(())
+}
+
+/// Lists and arrays are prefixed with their length as an `i32`.
+fn validate_len(len: usize, name: &'static str) -> Result<()> {
+    if len > i32::MAX as usize {
+        return Err(Error::LengthOverflow(name));
+    }
+    Ok(())
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.print(f, 0)
@@ -349,3 +1283,131 @@ impl<'a> From<&'a [i64]> for Value {
         Value::LongArray(t.into())
     }
 }
+
+#[cfg(feature = "serde")]
+use serde::{self, de};
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        // The NBT decoder recognises this name, and visits arrays in a way
+        // that `ValueVisitor` can tell apart from lists.
+        deserializer.deserialize_newtype_struct("__hematite_nbt_value__", ValueVisitor)
+    }
+}
+
+/// Visitor that maps each primitive onto the `Value` variant of the same
+/// width, so that the tag types survive buffering (e.g. for
+/// `#[serde(flatten)]` catch-all maps).
+///
+/// The serde data model does not distinguish between NBT lists and arrays.
+/// When reading NBT, the decoder visits byte arrays as byte buffers, and int
+/// and long arrays as a map with a single entry keyed by the same magic names
+/// used to serialize them. Other formats read all sequences as `Value::List`.
+#[cfg(feature = "serde")]
+struct ValueVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a valid NBT value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Byte(v as i8))
+    }
+
+    fn visit_i8<E>(self, v: i8) -> std::result::Result<Value, E> {
+        Ok(Value::Byte(v))
+    }
+
+    fn visit_i16<E>(self, v: i16) -> std::result::Result<Value, E> {
+        Ok(Value::Short(v))
+    }
+
+    fn visit_i32<E>(self, v: i32) -> std::result::Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Long(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> std::result::Result<Value, E>
+    where
+        E: de::Error,
+    {
+        if v > i64::MAX as u64 {
+            return Err(E::invalid_value(de::Unexpected::Unsigned(v), &self));
+        }
+        Ok(Value::Long(v as i64))
+    }
+
+    fn visit_f32<E>(self, v: f32) -> std::result::Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Value, E> {
+        Ok(Value::Double(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> std::result::Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(Value::ByteArray(v.iter().map(|&b| b as i8).collect()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Value, E> {
+        Ok(Value::ByteArray(v.into_iter().map(|b| b as i8).collect()))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        // The length comes from the input, so don't trust it for more than a
+        // modest preallocation.
+        let mut buf = Vec::with_capacity(cmp::min(seq.size_hint().unwrap_or(0), 4096));
+        while let Some(elem) = seq.next_element()? {
+            buf.push(elem);
+        }
+        Ok(Value::List(buf))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut buf = Map::new();
+        while let Some(k) = map.next_key::<String>()? {
+            if buf.is_empty() {
+                match k.as_str() {
+                    "__hematite_nbt_i32_array__" => return Ok(Value::IntArray(map.next_value()?)),
+                    "__hematite_nbt_i64_array__" => return Ok(Value::LongArray(map.next_value()?)),
+                    _ => (),
+                }
+            }
+            let v = map.next_value()?;
+            buf.insert(k, v);
+        }
+        Ok(Value::Compound(buf))
+    }
+}
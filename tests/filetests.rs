@@ -4,13 +4,17 @@
 
 #[macro_use]
 extern crate serde_derive;
+extern crate flate2;
 extern crate serde;
+extern crate serde_json;
 
 extern crate nbt;
 
 use std::fs::File;
 
-use nbt::de::{from_gzip_reader, from_reader};
+use flate2::read::GzDecoder;
+
+use nbt::de::{from_gzip_reader, from_reader, read_f64_list};
 
 // Include structure definitions.
 include!("data.rs.in");
@@ -44,6 +48,24 @@ fn deserialize_small2() {
     assert_eq!(nbt, read)
 }
 
+#[test]
+fn deserialize_small2_as_json() {
+    let mut file = File::open("tests/small2.nbt").unwrap();
+    let read: serde_json::Value = from_reader(&mut file).unwrap();
+    assert_eq!(read["aaa"]["1"], 17);
+    assert_eq!(read["bbb"]["3"], 287454020);
+    assert_eq!(read["bbb"].as_object().unwrap().len(), 3);
+}
+
+#[test]
+fn deserialize_arrays_as_json() {
+    let mut file = File::open("tests/arrays.nbt").unwrap();
+    let read: serde_json::Value = from_reader(&mut file).unwrap();
+    // Arrays become plain JSON arrays of numbers.
+    assert_eq!(read["ba"], serde_json::json!([-2, -1, 0, 1, 2]));
+    assert_eq!(read["la"], serde_json::json!([-2, -1, 0, 1, 2]));
+}
+
 #[test]
 fn deserialize_small3() {
     let nbt = Small3 {
@@ -181,6 +203,22 @@ fn deserialize_complex_player() {
     let _: PlayerData = from_gzip_reader(&mut file).unwrap();
 }
 
+#[test]
+fn read_complex_player_position() {
+    let file = File::open("tests/complex_player.dat").unwrap();
+    let pos = read_f64_list(GzDecoder::new(file), "Pos").unwrap();
+    assert_eq!(pos, vec![5325.151309704701, 67.0, 908.4680097186259]);
+
+    let mut file = File::open("tests/complex_player.dat").unwrap();
+    let player: PlayerData = from_gzip_reader(&mut file).unwrap();
+    assert_eq!(pos, player.position);
+
+    // Rotation is a list of floats rather than doubles.
+    let file = File::open("tests/complex_player.dat").unwrap();
+    let rotation = read_f64_list(GzDecoder::new(file), "Rotation");
+    assert_eq!(rotation.unwrap_err(), nbt::Error::TagMismatch(0x05, 0x06));
+}
+
 #[test]
 fn deserialize_level() {
     let mut file = File::open("tests/level.dat").unwrap();
@@ -4,10 +4,13 @@ extern crate serde;
 
 extern crate nbt;
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque};
 
 use serde::{Serialize, Serializer};
 
+include!("helpers.rs.in");
+
 /// Helper function that asserts data of type T can be serialized into and
 /// deserialized from `bytes`. `name` is an optional header for the top-level
 /// NBT compound.
@@ -92,6 +95,54 @@ fn roundtrip_primitives() {
     assert_roundtrip_eq(nbt, &bytes, Some("data"));
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct SpecialFloatNbt {
+    nan: f32,
+    inf: f64,
+    snan: f32,
+}
+
+#[test]
+fn roundtrip_special_floats() {
+    // A signaling NaN, with a payload that must not be quietened.
+    let snan = f32::from_bits(0x7f80_0001);
+    let nbt = SpecialFloatNbt {
+        nan: f32::NAN,
+        inf: f64::INFINITY,
+        snan,
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x05,
+                0x00, 0x03,
+                0x6e, 0x61, 0x6e,
+                0x7f, 0xc0, 0x00, 0x00,
+            0x06,
+                0x00, 0x03,
+                0x69, 0x6e, 0x66,
+                0x7f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x05,
+                0x00, 0x04,
+                0x73, 0x6e, 0x61, 0x6e,
+                0x7f, 0x80, 0x00, 0x01,
+        0x00
+    ];
+
+    // NaN != NaN, so compare bit patterns rather than using
+    // assert_roundtrip_eq.
+    let mut dst = Vec::new();
+    nbt::ser::to_writer(&mut dst, &nbt, None).unwrap();
+    assert_eq!(dst, bytes);
+
+    let read: SpecialFloatNbt = nbt::de::from_reader(&bytes[..]).unwrap();
+    assert_eq!(read.nan.to_bits(), f32::NAN.to_bits());
+    assert_eq!(read.inf, f64::INFINITY);
+    assert_eq!(read.snan.to_bits(), snan.to_bits());
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct BasicListNbt {
     data: Vec<i16>,
@@ -140,6 +191,81 @@ fn roundtrip_empty_list() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SetNbt {
+    ints: BTreeSet<i32>,
+    names: HashSet<String>,
+}
+
+#[test]
+fn deserialize_sets() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x69, 0x6e, 0x74, 0x73,
+                0x03,
+                0x00, 0x00, 0x00, 0x04,
+                0x00, 0x00, 0x00, 0x03,
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x00, 0x00, 0x03,
+                0x00, 0x00, 0x00, 0x02,
+            0x09,
+                0x00, 0x05,
+                0x6e, 0x61, 0x6d, 0x65, 0x73,
+                0x08,
+                0x00, 0x00, 0x00, 0x02,
+                0x00, 0x01, 0x61,
+                0x00, 0x01, 0x61,
+        0x00
+    ];
+
+    let read: SetNbt = nbt::de::from_reader(&bytes[..]).unwrap();
+
+    // Duplicates are dropped, and ordered sets are sorted.
+    assert_eq!(read.ints.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(read.names.len(), 1);
+    assert!(read.names.contains("a"));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct DequeNbt {
+    deque: VecDeque<i16>,
+    linked: LinkedList<i16>,
+}
+
+#[test]
+fn roundtrip_deque_and_linked_list() {
+    let nbt = DequeNbt {
+        deque: vec![1, 2].into_iter().collect(),
+        linked: vec![3].into_iter().collect(),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x05,
+                0x64, 0x65, 0x71, 0x75, 0x65,
+                0x02,
+                0x00, 0x00, 0x00, 0x02,
+                0x00, 0x01,
+                0x00, 0x02,
+            0x09,
+                0x00, 0x06,
+                0x6c, 0x69, 0x6e, 0x6b, 0x65, 0x64,
+                0x02,
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x03,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct NestedListNbt {
     data: Vec<Vec<i16>>,
@@ -172,6 +298,34 @@ fn roundtrip_nested_list() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[test]
+fn roundtrip_nested_empty_list() {
+    let nbt = NestedListNbt {
+        data: vec![vec![], vec![1]],
+    };
+
+    // Serde gives no access to the element type of an empty sequence, so an
+    // empty inner list is written with type TAG_End, as Minecraft does.
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x09, // Also a list.
+                0x00, 0x00, 0x00, 0x02,
+                0x00, // First list is empty.
+                0x00, 0x00, 0x00, 0x00,
+                0x02, // Second list has type short.
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x01,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct NestedArrayNbt {
     #[serde(serialize_with = "nested_i32_array")]
@@ -223,6 +377,298 @@ fn roundtrip_nested_array() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+struct UnsizedListNbt {
+    #[serde(serialize_with = "positive_values")]
+    data: Vec<i16>,
+}
+
+fn positive_values<S>(values: &[i16], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    // Filtering makes the length unknown to the serializer.
+    serializer.collect_seq(values.iter().filter(|&&v| v > 0))
+}
+
+#[test]
+fn serialize_unsized_list() {
+    let nbt = UnsizedListNbt {
+        data: vec![1, -1, 2, 0, 3],
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x02, // List type.
+                0x00, 0x00, 0x00, 0x03, // Length.
+                0x00, 0x01,
+                0x00, 0x02,
+                0x00, 0x03,
+        0x00
+    ];
+
+    let mut dst = Vec::new();
+    nbt::to_writer(&mut dst, &nbt, None).unwrap();
+    assert_eq!(bytes, dst);
+
+    let read: BasicListNbt = nbt::from_reader(&bytes[..]).unwrap();
+    assert_eq!(read.data, vec![1, 2, 3]);
+
+    let empty = UnsizedListNbt { data: vec![-1] };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x00, // Empty list type.
+                0x00, 0x00, 0x00, 0x00, // Length.
+        0x00
+    ];
+
+    let mut dst = Vec::new();
+    nbt::to_writer(&mut dst, &empty, None).unwrap();
+    assert_eq!(bytes, dst);
+}
+
+#[test]
+fn serialize_stringified_keys() {
+    let mut nbt = BTreeMap::new();
+    nbt.insert(1i32, 5i8);
+    nbt.insert(-20i32, 6i8);
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x03,
+                0x2d, 0x32, 0x30, // "-20"
+                0x06,
+            0x01,
+                0x00, 0x01,
+                0x31, // "1"
+                0x05,
+        0x00
+    ];
+
+    let mut dst = Vec::new();
+    let mut encoder = nbt::ser::Encoder::new(&mut dst, None).stringify_keys(true);
+    nbt.serialize(&mut encoder).unwrap();
+    assert_eq!(bytes, dst);
+
+    let read: HashMap<String, i8> = nbt::from_reader(&bytes[..]).unwrap();
+    assert_eq!(read["-20"], 6);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "id")]
+enum Item {
+    #[serde(rename = "minecraft:diamond_sword")]
+    // Fields of internally tagged enums are buffered by serde before they
+    // are deserialized, so booleans must be read as plain bytes.
+    Sword { damage: i16, unbreakable: i8 },
+    #[serde(rename = "minecraft:stone")]
+    Stone { count: i8 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct ItemsNbt {
+    items: Vec<Item>,
+}
+
+#[test]
+fn roundtrip_internally_tagged_enum() {
+    let nbt = ItemsNbt {
+        items: vec![
+            Item::Sword {
+                damage: 5,
+                unbreakable: 1,
+            },
+            Item::Stone { count: 64 },
+        ],
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x05,
+                0x69, 0x74, 0x65, 0x6d, 0x73, // "items"
+                0x0a,
+                0x00, 0x00, 0x00, 0x02,
+                // First compound.
+                0x08,
+                    0x00, 0x02,
+                    0x69, 0x64, // "id"
+                    0x00, 0x17,
+                    0x6d, 0x69, 0x6e, 0x65, 0x63, 0x72, 0x61, 0x66, 0x74, 0x3a,
+                    0x64, 0x69, 0x61, 0x6d, 0x6f, 0x6e, 0x64, 0x5f,
+                    0x73, 0x77, 0x6f, 0x72, 0x64, // "minecraft:diamond_sword"
+                0x02,
+                    0x00, 0x06,
+                    0x64, 0x61, 0x6d, 0x61, 0x67, 0x65, // "damage"
+                    0x00, 0x05,
+                0x01,
+                    0x00, 0x0b,
+                    0x75, 0x6e, 0x62, 0x72, 0x65, 0x61, 0x6b, 0x61, 0x62,
+                    0x6c, 0x65, // "unbreakable"
+                    0x01,
+                0x00,
+                // Second compound.
+                0x08,
+                    0x00, 0x02,
+                    0x69, 0x64, // "id"
+                    0x00, 0x0f,
+                    0x6d, 0x69, 0x6e, 0x65, 0x63, 0x72, 0x61, 0x66, 0x74, 0x3a,
+                    0x73, 0x74, 0x6f, 0x6e, 0x65, // "minecraft:stone"
+                0x01,
+                    0x00, 0x05,
+                    0x63, 0x6f, 0x75, 0x6e, 0x74, // "count"
+                    0x40,
+                0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SubStruct {
+    id: i8,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CompoundListNbt {
+    #[serde(with = "nbt::list_of")]
+    data: Vec<SubStruct>,
+}
+
+#[test]
+fn roundtrip_compound_list() {
+    let nbt = CompoundListNbt {
+        data: vec![SubStruct { id: 1 }, SubStruct { id: 2 }],
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x0a, // Compound.
+                0x00, 0x00, 0x00, 0x02,
+                // First compound.
+                0x01,
+                    0x00, 0x02,
+                    0x69, 0x64,
+                    0x01,
+                0x00,
+                // Second compound.
+                0x01,
+                    0x00, 0x02,
+                    0x69, 0x64,
+                    0x02,
+                0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[test]
+fn stream_list_elements() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01, // Skipped.
+                0x00, 0x01,
+                0x61,
+                0x05,
+            0x0a,
+                0x00, 0x05,
+                0x4c, 0x65, 0x76, 0x65, 0x6c, // "Level"
+                0x09,
+                    0x00, 0x04,
+                    0x64, 0x61, 0x74, 0x61,
+                    0x0a, // Compound.
+                    0x00, 0x00, 0x00, 0x02,
+                    // First compound.
+                    0x01,
+                        0x00, 0x02,
+                        0x69, 0x64,
+                        0x01,
+                    0x00,
+                    // Second compound.
+                    0x01,
+                        0x00, 0x02,
+                        0x69, 0x64,
+                        0x02,
+                    0x00,
+            0x00,
+        0x00
+    ];
+
+    let iter = nbt::de::seq_from_reader(&bytes[..], "Level.data").unwrap();
+    let items: Vec<SubStruct> = iter.collect::<nbt::Result<_>>().unwrap();
+    assert_eq!(items, vec![SubStruct { id: 1 }, SubStruct { id: 2 }]);
+
+    let missing = nbt::de::seq_from_reader::<_, SubStruct>(&bytes[..], "Level.other");
+    assert!(missing.is_err());
+}
+
+#[test]
+fn stream_long_array_elements() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0c, // Skipped.
+                0x00, 0x01,
+                0x61,
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07,
+            0x0a,
+                0x00, 0x05,
+                0x4c, 0x65, 0x76, 0x65, 0x6c, // "Level"
+                0x0c,
+                    0x00, 0x04,
+                    0x64, 0x61, 0x74, 0x61,
+                    0x00, 0x00, 0x00, 0x03,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+                    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0x00,
+        0x00
+    ];
+
+    let mut sum = 0;
+    let mut count = 0;
+    nbt::de::for_each_long(&bytes[..], "Level.data", |long| {
+        sum += long;
+        count += 1;
+    })
+    .unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(sum, 0x1_0000_0000);
+
+    let missing = nbt::de::for_each_long(&bytes[..], "Level.other", |_| {});
+    assert!(missing.is_err());
+
+    let mismatch = nbt::de::for_each_long(&bytes[..], "Level", |_| {});
+    assert_eq!(mismatch.unwrap_err(), nbt::Error::TagMismatch(0x0a, 0x0c));
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct ByteArrayNbt {
     #[serde(serialize_with = "nbt::i8_array")]
@@ -250,6 +696,33 @@ fn roundtrip_byte_array() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BytesNbt {
+    #[serde(with = "as_bytes")]
+    data: Vec<u8>,
+}
+
+#[test]
+fn roundtrip_bytes() {
+    let nbt = BytesNbt {
+        data: vec![1, 2, 0xff],
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x07,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x00, 0x00, 0x00, 0x03, // Length of array.
+                0x01, 0x02, 0xff,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct IntArrayNbt {
     #[serde(serialize_with = "nbt::i32_array")]
@@ -298,6 +771,35 @@ fn roundtrip_int_array() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UuidNbt {
+    uuid: u128,
+}
+
+#[test]
+fn roundtrip_u128_uuid() {
+    let nbt = UuidNbt {
+        uuid: 0x00000001_00000002_ffffffff_80000000,
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0b,
+                0x00, 0x04,
+                0x75, 0x75, 0x69, 0x64,
+                0x00, 0x00, 0x00, 0x04,
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x00, 0x00, 0x02,
+                0xff, 0xff, 0xff, 0xff,
+                0x80, 0x00, 0x00, 0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct LongArrayNbt {
     #[serde(serialize_with = "nbt::ser::i64_array")]
@@ -465,6 +967,30 @@ fn roundtrip_newtype_struct() {
     assert_roundtrip_eq(nbt, &bytes, None);
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RootNbt(NewByteNbt);
+
+#[test]
+fn roundtrip_named_root_newtype_struct() {
+    // Newtypes around the root compound, even nested ones, read the header
+    // only once.
+    let nbt = RootNbt(NewByteNbt(ByteNbt { data: 100 }));
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x05,
+            0x4c, 0x65, 0x76, 0x65, 0x6c,
+            0x01,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x64,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, Some("Level"));
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct NestedByteNbt {
     data: ByteNbt,
@@ -571,8 +1097,10 @@ fn roundtrip_hashmap() {
 #[test]
 fn ser_blob_array() {
     let mut blob = nbt::Blob::new();
-    blob.insert("larr", nbt::Value::LongArray(vec![456, 123])).unwrap();
-    blob.insert("iarr", nbt::Value::IntArray(vec![123, 456])).unwrap();
+    blob.insert("larr", nbt::Value::LongArray(vec![456, 123]))
+        .unwrap();
+    blob.insert("iarr", nbt::Value::IntArray(vec![123, 456]))
+        .unwrap();
 
     #[rustfmt::skip]
     let bytes = vec![
@@ -598,3 +1126,360 @@ fn ser_blob_array() {
     nbt::ser::to_writer(&mut dst, &blob, None).expect("NBT serialization.");
     assert_eq!(bytes, &dst[..]);
 }
+
+#[test]
+fn roundtrip_blob_arrays() {
+    let mut blob = nbt::Blob::new();
+    blob.insert("barr", nbt::Value::ByteArray(vec![1, 2, -3]))
+        .unwrap();
+    blob.insert("iarr", nbt::Value::IntArray(vec![123, -456]))
+        .unwrap();
+    blob.insert("larr", nbt::Value::LongArray(vec![456, -123]))
+        .unwrap();
+    blob.insert("list", nbt::Value::List(vec![nbt::Value::Int(7)]))
+        .unwrap();
+
+    let mut dst = Vec::new();
+    nbt::ser::to_writer(&mut dst, &blob, None).expect("NBT serialization.");
+    let read: nbt::Blob = nbt::de::from_reader(&dst[..]).expect("NBT deserialization.");
+
+    assert_eq!(read, blob);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct FlattenedNbt {
+    data: i8,
+    #[serde(flatten)]
+    extra: HashMap<String, nbt::Value>,
+}
+
+#[test]
+fn deserialize_flattened_extra_fields() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x01,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x64,
+            0x03,
+                0x00, 0x03,
+                0x69, 0x6e, 0x74,
+                0x00, 0x00, 0x00, 0x64,
+            0x08,
+                0x00, 0x04,
+                0x6e, 0x61, 0x6d, 0x65,
+                0x00, 0x09,
+                0x48, 0x65, 0x72, 0x6f, 0x62, 0x72, 0x69, 0x6e, 0x65,
+            0x0a,
+                0x00, 0x05,
+                0x69, 0x6e, 0x6e, 0x65, 0x72,
+                0x02,
+                    0x00, 0x05,
+                    0x73, 0x68, 0x6f, 0x72, 0x74,
+                    0x00, 0x01,
+            0x00,
+        0x00
+    ];
+
+    let mut inner = nbt::Map::new();
+    inner.insert("short".to_string(), nbt::Value::Short(1));
+
+    let mut extra = HashMap::new();
+    extra.insert("int".to_string(), nbt::Value::Int(100));
+    extra.insert(
+        "name".to_string(),
+        nbt::Value::String("Herobrine".to_string()),
+    );
+    extra.insert("inner".to_string(), nbt::Value::Compound(inner));
+
+    let read: FlattenedNbt = nbt::de::from_reader(&bytes[..]).expect("NBT deserialization.");
+    assert_eq!(read, FlattenedNbt { data: 100, extra });
+}
+
+#[test]
+fn roundtrip_little_endian() {
+    let nbt = PrimitiveNbt {
+        byte: 100,
+        short: 100,
+        int: 100,
+        long: 100,
+        float: 20.0,
+        double: 20.0,
+        string: "Herobrine".to_string(),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x04, 0x00, // Header: "data"
+            0x64, 0x61, 0x74, 0x61,
+            0x01,
+                0x04, 0x00,
+                0x62, 0x79, 0x74, 0x65,
+                0x64,
+            0x02,
+                0x05, 0x00,
+                0x73, 0x68, 0x6f, 0x72, 0x74,
+                0x64, 0x00,
+            0x03,
+                0x03, 0x00,
+                0x69, 0x6e, 0x74,
+                0x64, 0x00, 0x00, 0x00,
+            0x04,
+                0x04, 0x00,
+                0x6c, 0x6f, 0x6e, 0x67,
+                0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x05,
+                0x05, 0x00,
+                0x66, 0x6c, 0x6f, 0x61, 0x74,
+                0x00, 0x00, 0xa0, 0x41,
+            0x06,
+                0x06, 0x00,
+                0x64, 0x6f, 0x75, 0x62, 0x6c, 0x65,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0x40,
+            0x08,
+                0x06, 0x00,
+                0x73, 0x74, 0x72, 0x69, 0x6e, 0x67,
+                0x09, 0x00,
+                0x48, 0x65, 0x72, 0x6f, 0x62, 0x72, 0x69, 0x6e, 0x65,
+        0x00
+    ];
+
+    let mut dst = Vec::with_capacity(bytes.len());
+    nbt::ser::to_writer_endian(&mut dst, &nbt, Some("data"), nbt::Endianness::LittleEndian)
+        .expect("NBT serialization.");
+    assert_eq!(bytes, dst);
+
+    let read: PrimitiveNbt = nbt::de::from_reader_endian(&bytes[..], nbt::Endianness::LittleEndian)
+        .expect("NBT deserialization.");
+    assert_eq!(read, nbt);
+
+    let mut options = nbt::ReadOptions::default();
+    options.endian = nbt::Endianness::LittleEndian;
+    let read: PrimitiveNbt =
+        nbt::de::from_reader_with(&bytes[..], &options).expect("NBT deserialization.");
+    assert_eq!(read, nbt);
+
+    // Lists and arrays also have little-endian lengths.
+    let nbt = IntArrayNbt { data: vec![1, 2] };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0b,
+                0x04, 0x00,
+                0x64, 0x61, 0x74, 0x61,
+                0x02, 0x00, 0x00, 0x00, // Length.
+                0x01, 0x00, 0x00, 0x00,
+                0x02, 0x00, 0x00, 0x00,
+        0x00
+    ];
+
+    let mut dst = Vec::with_capacity(bytes.len());
+    nbt::ser::to_writer_endian(&mut dst, &nbt, None, nbt::Endianness::LittleEndian)
+        .expect("NBT serialization.");
+    assert_eq!(bytes, dst);
+
+    let read: IntArrayNbt = nbt::de::from_reader_endian(&bytes[..], nbt::Endianness::LittleEndian)
+        .expect("NBT deserialization.");
+    assert_eq!(read, nbt);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Action {
+    A(i32),
+    B(String),
+    C,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct EnumNbt {
+    data: Action,
+}
+
+#[test]
+fn roundtrip_newtype_variant() {
+    let nbt = EnumNbt {
+        data: Action::A(100),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0a,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x03,
+                    0x00, 0x01,
+                    0x41,
+                    0x00, 0x00, 0x00, 0x64,
+            0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+
+    let nbt = EnumNbt {
+        data: Action::B("Herobrine".to_string()),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0a,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x08,
+                    0x00, 0x01,
+                    0x42,
+                    0x00, 0x09,
+                    0x48, 0x65, 0x72, 0x6f, 0x62, 0x72, 0x69, 0x6e, 0x65,
+            0x00,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[test]
+fn roundtrip_unit_variant() {
+    let nbt = EnumNbt { data: Action::C };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x08,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x00, 0x01,
+                0x43,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CowNbt {
+    name: Cow<'static, str>,
+}
+
+#[test]
+fn roundtrip_cow_str() {
+    let nbt = CowNbt {
+        name: Cow::Borrowed("Herobrine"),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x08,
+                0x00, 0x04,
+                0x6e, 0x61, 0x6d, 0x65,
+                0x00, 0x09,
+                0x48, 0x65, 0x72, 0x6f, 0x62, 0x72, 0x69, 0x6e, 0x65,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+
+    // Supplementary characters are stored as CESU-8 surrogate pairs.
+    let nbt = CowNbt {
+        name: Cow::Owned("\u{10401}".to_string()),
+    };
+
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x08,
+                0x00, 0x04,
+                0x6e, 0x61, 0x6d, 0x65,
+                0x00, 0x06,
+                0xed, 0xa0, 0x81, 0xed, 0xb0, 0x81,
+        0x00
+    ];
+
+    assert_roundtrip_eq(nbt, &bytes, None);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct HintedNbt {
+    count: nbt::Value,
+    items: Vec<nbt::Value>,
+}
+
+#[test]
+fn deserialize_with_type_hints() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x02,
+                0x00, 0x05,
+                0x63, 0x6f, 0x75, 0x6e, 0x74, // "count"
+                0x01, 0x2c,
+            0x09,
+                0x00, 0x05,
+                0x69, 0x74, 0x65, 0x6d, 0x73, // "items"
+                0x01,
+                0x00, 0x00, 0x00, 0x02,
+                0x01, 0x02,
+        0x00
+    ];
+
+    // Without hints, values keep the width they were stored with.
+    let read: HintedNbt = nbt::de::from_reader(&bytes[..]).unwrap();
+    assert_eq!(read.count, nbt::Value::Short(300));
+    assert_eq!(read.items, vec![nbt::Value::Byte(1), nbt::Value::Byte(2)]);
+
+    // A hint for a list applies to all of its elements, and the elements
+    // can't be hinted one by one.
+    let mut options = nbt::ReadOptions::default();
+    options.type_hints.insert("count".to_string(), 0x04);
+    options.type_hints.insert("items".to_string(), 0x03);
+    options.type_hints.insert("items.1".to_string(), 0x02);
+    let read: HintedNbt = nbt::de::from_reader_with(&bytes[..], &options).unwrap();
+    assert_eq!(read.count, nbt::Value::Long(300));
+    assert_eq!(read.items, vec![nbt::Value::Int(1), nbt::Value::Int(2)]);
+
+    // A hint too narrow for the stored value is an error.
+    let mut options = nbt::ReadOptions::default();
+    options.type_hints.insert("count".to_string(), 0x01);
+    let read: nbt::Result<HintedNbt> = nbt::de::from_reader_with(&bytes[..], &options);
+    assert_eq!(read.unwrap_err(), nbt::Error::TagMismatch(0x02, 0x01));
+}
+
+#[test]
+fn serialize_bare_values() {
+    let mut dst = Vec::new();
+    nbt::ser::to_bare_writer(&mut dst, &-100000i32).unwrap();
+    assert_eq!(dst, vec![0xff, 0xfe, 0x79, 0x60]);
+    let read = nbt::raw::read_bare_int(&mut &dst[..], nbt::Endianness::BigEndian).unwrap();
+    assert_eq!(read, -100000);
+
+    let mut dst = Vec::new();
+    nbt::ser::to_bare_writer(&mut dst, &vec!["a", "b"]).unwrap();
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x08,
+        0x00, 0x00, 0x00, 0x02,
+            0x00, 0x01, 0x61,
+            0x00, 0x01, 0x62
+    ];
+    assert_eq!(dst, bytes);
+
+    #[derive(Serialize)]
+    struct Longs(#[serde(serialize_with = "nbt::i64_array")] Vec<i64>);
+
+    let mut dst = Vec::new();
+    nbt::ser::to_bare_writer(&mut dst, &Longs(vec![1])).unwrap();
+    assert_eq!(dst, vec![0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+}
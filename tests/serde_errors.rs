@@ -4,9 +4,11 @@ extern crate serde;
 
 extern crate nbt;
 
-use nbt::de::from_reader;
+use nbt::de::{from_reader, from_reader_with, read_f64_list};
 use nbt::ser::to_writer;
-use nbt::{Error, Result};
+use nbt::{Blob, Error, ReadOptions, Result};
+
+include!("helpers.rs.in");
 
 #[test]
 fn no_root_compound() {
@@ -118,3 +120,357 @@ fn non_boolean_byte() {
         _ => panic!("encountered an unexpected error"),
     }
 }
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct ListNbt {
+    data: Vec<i16>,
+}
+
+#[test]
+fn incomplete_list() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x02,
+                0x00, 0x00, 0x00, 0x03,
+                0x00, 0x01,
+                0x00, 0x02,
+                0x00
+    ];
+
+    let read: Result<ListNbt> = from_reader(&bytes[..]);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::IncompleteList(read, expected) => {
+            assert_eq!(read, 2);
+            assert_eq!(expected, 3);
+        }
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[test]
+fn oversized_list_length() {
+    // A list claiming i32::MAX compounds, where the trailing end tags are read
+    // as two empty ones.
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x01,
+                0x6c,
+                0x0a,
+                0x7f, 0xff, 0xff, 0xff,
+            0x00,
+        0x00
+    ];
+
+    let read: Result<Blob> = from_reader(&bytes[..]);
+
+    match read.unwrap_err() {
+        Error::IncompleteList(read, expected) => {
+            assert_eq!(read, 2);
+            assert_eq!(expected, i32::MAX);
+        }
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[test]
+fn incomplete_f64_list() {
+    // A list claiming i32::MAX doubles, with only one present.
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x03,
+                0x50, 0x6f, 0x73,
+                0x06,
+                0x7f, 0xff, 0xff, 0xff,
+                0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00
+    ];
+
+    let read = read_f64_list(&bytes[..], "Pos");
+
+    match read.unwrap_err() {
+        Error::IncompleteNbtValue => (),
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct BytesNbt {
+    #[serde(deserialize_with = "as_bytes::deserialize")]
+    data: Vec<u8>,
+}
+
+#[test]
+fn incomplete_bytes() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x07,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x00, 0x00, 0x00, 0x03,
+                0x01, 0x02
+    ];
+
+    let read: Result<BytesNbt> = from_reader(&bytes[..]);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::IncompleteNbtValue => (),
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[test]
+fn depth_limit_exceeded() {
+    let bytes = nested_lists(1000);
+    let read: Result<Blob> = from_reader(&bytes[..]);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::DepthLimitExceeded => (),
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[test]
+fn size_limit_exceeded() {
+    let mut blob = Blob::new();
+    for i in 0..100 {
+        blob.insert(format!("entry{}", i), i as i8).unwrap();
+    }
+    let mut bytes = Vec::new();
+    blob.to_writer(&mut bytes).unwrap();
+
+    let mut options = ReadOptions::default();
+    options.max_nodes = 50;
+    let read: Result<Blob> = from_reader_with(&bytes[..], &options);
+
+    assert!(read.is_err());
+    match read.unwrap_err() {
+        Error::SizeLimitExceeded => (),
+        _ => panic!("encountered an unexpected error"),
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Abilities {
+    #[serde(rename = "flySpeed")]
+    fly_speed: f32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Player {
+    abilities: Abilities,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct LevelData {
+    #[serde(rename = "Player")]
+    player: Player,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Level {
+    #[serde(rename = "Data")]
+    data: LevelData,
+}
+
+#[test]
+fn error_path() {
+    // flySpeed is stored as a string rather than a float.
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x0a,
+                0x00, 0x04,
+                0x44, 0x61, 0x74, 0x61,
+                0x0a,
+                    0x00, 0x06,
+                    0x50, 0x6c, 0x61, 0x79, 0x65, 0x72,
+                    0x0a,
+                        0x00, 0x09,
+                        0x61, 0x62, 0x69, 0x6c, 0x69, 0x74, 0x69, 0x65, 0x73,
+                        0x08,
+                            0x00, 0x08,
+                            0x66, 0x6c, 0x79, 0x53, 0x70, 0x65, 0x65, 0x64,
+                            0x00, 0x01,
+                            0x78,
+                    0x00,
+                0x00,
+            0x00,
+        0x00
+    ];
+
+    // By default, the error is returned as it is.
+    let read: Result<Level> = from_reader(&bytes[..]);
+    match read.unwrap_err() {
+        Error::Serde(_) => (),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+
+    let mut options = ReadOptions::default();
+    options.error_paths = true;
+    let read: Result<Level> = from_reader_with(&bytes[..], &options);
+
+    let err = read.unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with(" at Data.Player.abilities.flySpeed"));
+    match err {
+        Error::AtPath(ref path, ref inner) => {
+            assert_eq!(path, "Data.Player.abilities.flySpeed");
+            match **inner {
+                Error::Serde(_) => (),
+                ref e => panic!("encountered an unexpected error: {}", e),
+            }
+        }
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct ByteListNbt {
+    data: Vec<ByteNbt>,
+}
+
+#[test]
+fn error_path_in_list() {
+    #[rustfmt::skip]
+    let bytes = vec![
+        0x0a,
+            0x00, 0x00,
+            0x09,
+                0x00, 0x04,
+                0x64, 0x61, 0x74, 0x61,
+                0x0a,
+                0x00, 0x00, 0x00, 0x02,
+                    0x01,
+                        0x00, 0x04,
+                        0x64, 0x61, 0x74, 0x61,
+                        0x01,
+                    0x00,
+                    0x02,
+                        0x00, 0x04,
+                        0x64, 0x61, 0x74, 0x61,
+                        0x01, 0x00,
+                    0x00,
+        0x00
+    ];
+
+    let mut options = ReadOptions::default();
+    options.error_paths = true;
+    let read: Result<ByteListNbt> = from_reader_with(&bytes[..], &options);
+
+    match read.unwrap_err() {
+        Error::AtPath(ref path, _) => assert_eq!(path, "data.1.data"),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn non_string_map_key() {
+    let mut nbt = std::collections::BTreeMap::new();
+    nbt.insert(1i32, 5i8);
+
+    let mut dst = Vec::new();
+    match to_writer(&mut dst, &nbt, None).unwrap_err() {
+        Error::NonStringMapKey => (),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Mixed {
+    Int(i32),
+    Text(String),
+}
+
+#[derive(Debug, Serialize)]
+struct MixedNbt {
+    items: Vec<Mixed>,
+}
+
+/// A sequence whose length is not known until it has been serialized.
+struct Unsized<'a>(&'a [Mixed]);
+
+impl<'a> serde::Serialize for Unsized<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().filter(|_| true))
+    }
+}
+
+#[derive(Serialize)]
+struct UnsizedNbt<'a> {
+    items: Unsized<'a>,
+}
+
+#[test]
+fn heterogeneous_list() {
+    let items = vec![Mixed::Int(1), Mixed::Text("two".to_string())];
+
+    let mut dst = Vec::new();
+    match to_writer(&mut dst, &MixedNbt { items }, None).unwrap_err() {
+        Error::HeterogeneousList => (),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+
+    let items = [
+        Mixed::Int(1),
+        Mixed::Int(2),
+        Mixed::Text("three".to_string()),
+    ];
+    let nbt = UnsizedNbt {
+        items: Unsized(&items),
+    };
+    let mut dst = Vec::new();
+    match to_writer(&mut dst, &nbt, None).unwrap_err() {
+        Error::HeterogeneousList => (),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+
+    // Lists whose elements agree are still written.
+    let items = vec![Mixed::Int(1), Mixed::Int(2)];
+    let mut dst = Vec::new();
+    to_writer(&mut dst, &MixedNbt { items }, None).unwrap();
+}
+
+#[test]
+fn bare_compound() {
+    #[derive(Serialize)]
+    struct Compound {
+        data: i8,
+    }
+
+    let mut dst = Vec::new();
+    match nbt::ser::to_bare_writer(&mut dst, &Compound { data: 1 }).unwrap_err() {
+        Error::UnrepresentableType(_) => (),
+        e => panic!("encountered an unexpected error: {}", e),
+    }
+    assert!(dst.is_empty());
+}